@@ -1,113 +1,2390 @@
 use std::{
-    collections::HashMap,
-    sync::{Arc, Weak},
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    time::{Duration, Instant},
 };
 
-use async_recursion::async_recursion;
-use lapin::{Channel, Connection};
+use lapin::{
+    options::{BasicQosOptions, ConfirmSelectOptions},
+    protocol::constants::REPLY_SUCCESS,
+    types::{AMQPValue, ChannelId},
+    uri::AMQPUri,
+    Channel, Connection, ConnectionProperties,
+};
+use tokio::{
+    runtime::Handle,
+    sync::{broadcast, oneshot},
+    task::JoinSet,
+};
 
+use crate::backoff::Backoff;
+use crate::channel_lease::ChannelLease;
+use crate::channel_purpose::ChannelPurpose;
+use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+use crate::close_report::{CloseReport, UnclosedChannel, UnclosedConnection};
 use crate::config::amqp_connect_config::AmqpConnectConfig;
+use crate::config::backoff_config::BackoffConfig;
+use crate::config::blocked_config::BlockedPolicy;
+use crate::config::channel_profile::ChannelProfile;
+use crate::config::janitor_config::JanitorConfig;
+use crate::config::pool_config::PoolConfig;
+use crate::config::warm_up_config::WarmUpConfig;
+use crate::connect_rate_limiter::ConnectRateLimiter;
+use crate::connection_name::{hostname, render_connection_name, ConnectionNameContext};
+use crate::connection_selector::{ConnectionCandidate, ConnectionSelector};
 use crate::error::{Error, ErrorKind};
+use crate::events::WrapperEvent;
+use crate::read_clock::{ReadClock, ReadTrackingReactor};
+use crate::stats::{ConnectionStats, PoolStats, WrapperStats};
+use crate::transaction::{Transaction, TRANSACTION_PROFILE};
+
+// Events kept for the receivers lagging behind, the oldest ones being dropped first.
+const EVENTS_CAPACITY: usize = 64;
+
+struct WrappedConnection {
+    pub id: String,
+    pub connection: Connection,
+    // Broker node the connection is attached to, as `host:port`.
+    pub node: String,
+    pub opened_at: Instant,
+    // Channels the broker allows on the connection, as negotiated during the handshake.
+    pub channel_max: Option<u16>,
+    // Heartbeat interval negotiated with the broker, in seconds.
+    pub heartbeat: Option<u16>,
+    pub read_clock: Arc<ReadClock>,
+}
+
+impl WrappedConnection {
+    pub fn new(
+        id: String,
+        connection: Connection,
+        node: String,
+        read_clock: Arc<ReadClock>,
+    ) -> WrappedConnection {
+        // a zero channel_max leaves the number of channels unbounded
+        let channel_max = match connection.configuration().channel_max() {
+            0 => None,
+            channel_max => Some(channel_max),
+        };
+
+        let heartbeat = match connection.configuration().heartbeat() {
+            0 => None,
+            heartbeat => Some(heartbeat),
+        };
+
+        WrappedConnection {
+            id,
+            connection,
+            node,
+            opened_at: Instant::now(),
+            channel_max,
+            heartbeat,
+            read_clock,
+        }
+    }
+
+    /// Whole heartbeat intervals elapsed since the last frame was read from the
+    /// broker, 0 when no heartbeat was negotiated.
+    fn missed_heartbeats(&self) -> u32 {
+        match self.heartbeat {
+            Some(heartbeat) => {
+                (self.read_clock.since_last_read().as_secs() / heartbeat as u64) as u32
+            }
+            None => 0,
+        }
+    }
+}
+
+/// A connection of the pool along with the bookkeeping of its channels.
+struct PooledConnection {
+    connection: Arc<WrappedConnection>,
+    // Channels currently leased from this connection.
+    leased_channels: HashMap<ChannelId, Channel>,
+    // Channels being created on this connection, counted against its limit.
+    pending_channels: usize,
+    // Released channels kept open to be leased again.
+    idle_channels: Vec<IdleChannel>,
+    // Set once the broker refused to open more channels on this connection.
+    full: bool,
+    // Last time a channel was leased from or given back to this connection.
+    last_used: Instant,
+    // Whether the broker blocked the connection, as last polled.
+    blocked: bool,
+}
+
+impl PooledConnection {
+    fn new(connection: Arc<WrappedConnection>) -> PooledConnection {
+        PooledConnection {
+            connection,
+            leased_channels: HashMap::new(),
+            pending_channels: 0,
+            idle_channels: Vec::new(),
+            full: false,
+            last_used: Instant::now(),
+            blocked: false,
+        }
+    }
+
+    fn channel_count(&self) -> usize {
+        self.leased_channels.len() + self.pending_channels + self.idle_channels.len()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connection.connection.status().connected()
+    }
+
+    /// Whether another channel fits on the connection, within both the pool limit and
+    /// the channel_max negotiated with the broker, so that a new connection is opened
+    /// before the broker has to refuse a channel.
+    fn has_room(&self, max_channels_per_connection: Option<u16>) -> bool {
+        if self.full {
+            return false;
+        }
+
+        match max_channels_per_connection
+            .into_iter()
+            .chain(self.connection.channel_max)
+            .min()
+        {
+            Some(max_channels) => self.channel_count() < max_channels as usize,
+            None => true,
+        }
+    }
+
+    /// Whether the connection outlived `max_lifetime` and is being rotated out.
+    fn is_expired(&self, max_lifetime: Option<Duration>) -> bool {
+        max_lifetime.is_some_and(|max_lifetime| self.connection.opened_at.elapsed() >= max_lifetime)
+    }
+
+    fn is_idle_for(&self, idle_timeout: Duration) -> bool {
+        self.leased_channels.is_empty()
+            && self.pending_channels == 0
+            && self.last_used.elapsed() >= idle_timeout
+    }
+
+    fn stats(&self) -> ConnectionStats {
+        let live_channels = self
+            .leased_channels
+            .values()
+            .chain(
+                self.idle_channels
+                    .iter()
+                    .map(|idle_channel| &idle_channel.channel),
+            )
+            .filter(|channel| channel.status().connected())
+            .count();
+
+        ConnectionStats {
+            connection_id: self.connection.id.clone(),
+            node: self.connection.node.clone(),
+            age_ms: self.connection.opened_at.elapsed().as_millis() as u64,
+            state: format!("{:?}", self.connection.connection.status().state()).to_lowercase(),
+            blocked: self.connection.connection.status().blocked(),
+            channel_max: self.connection.channel_max,
+            heartbeat_secs: self.connection.heartbeat,
+            missed_heartbeats: self.connection.missed_heartbeats(),
+            leased_channels: self.leased_channels.len(),
+            idle_channels: self.idle_channels.len(),
+            pending_channels: self.pending_channels,
+            live_channels,
+            dead_channels: self.leased_channels.len() + self.idle_channels.len() - live_channels,
+        }
+    }
+}
+
+/// Events counted over the lifetime of a pool.
+#[derive(Default)]
+struct PoolCounters {
+    channels_created: u64,
+    connect_failures: u64,
+    limit_hits: u64,
+    dead_connections: u64,
+    heartbeat_timeouts: u64,
+    discarded_channels: u64,
+    rotated_connections: u64,
+}
+
+/// Released channel kept open, along with the profile it was opened with.
+struct IdleChannel {
+    channel: Channel,
+    profile: Option<String>,
+}
+
+/// Channel slot reserved for a caller, along with an idle channel to reuse if any.
+struct Reservation {
+    connection: Arc<WrappedConnection>,
+    idle_channel: Option<IdleChannel>,
+}
+
+/// Outcome of one attempt at leasing a channel.
+enum Attempt {
+    Leased(Box<ChannelLease>),
+    // The attempt failed in a way another one may not, for the given reason.
+    Retry(String),
+}
+
+/// Caller waiting for a channel slot to be handed over.
+struct Waiter {
+    sender: oneshot::Sender<Reservation>,
+    profile: Option<String>,
+}
+
+/// Receiving end of a waiter, giving back the channel slot handed over to it once
+/// the caller stopped waiting, e.g. when its attempt reached the retry deadline.
+struct WaiterReceiver<'a> {
+    receiver: oneshot::Receiver<Reservation>,
+    wrapper: &'a AmqpWrapper,
+    pool_key: &'a PoolKey,
+}
+
+impl Drop for WaiterReceiver<'_> {
+    fn drop(&mut self) {
+        self.receiver.close();
+
+        if let Ok(reservation) = self.receiver.try_recv() {
+            let _ = self.wrapper.cancel_reservation(self.pool_key, reservation);
+        }
+    }
+}
+
+/// Channel slot held by an attempt at leasing a channel, given back to the pool
+/// along with the channel created in it when the attempt is dropped before leasing
+/// it, e.g. when it reached the retry deadline.
+struct ReservedSlot<'a> {
+    wrapper: &'a AmqpWrapper,
+    pool_key: &'a PoolKey,
+    reservation: Option<Reservation>,
+    channel: Option<Channel>,
+}
+
+impl ReservedSlot<'_> {
+    /// Leaves the slot to the caller, which either leased or released it.
+    fn disarm(&mut self) {
+        self.reservation = None;
+        self.channel = None;
+    }
+}
+
+impl Drop for ReservedSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(channel) = self.channel.take() {
+            close_channel(channel);
+        }
+
+        if let Some(reservation) = self.reservation.take() {
+            let _ = self.wrapper.cancel_reservation(self.pool_key, reservation);
+        }
+    }
+}
+
+/// Connections and channels shared by every clone of an `AmqpWrapper`.
+///
+/// The lock protecting it must never be held across an `.await`.
+struct PoolState {
+    pool_config: PoolConfig,
+    selector: Arc<dyn ConnectionSelector>,
+    connections: Vec<PooledConnection>,
+    waiters: VecDeque<Waiter>,
+    // Set while a task is replacing the connections lost by the pool.
+    recovering: bool,
+    // Set once the wrapper has been closed, for good.
+    closing: bool,
+    // Delays between the connections opened on demand, while they keep failing.
+    connect_backoff: Option<Backoff>,
+    // No connection is opened on demand before then, after a failed one.
+    next_connect_at: Option<Instant>,
+    counters: PoolCounters,
+}
+
+impl PoolState {
+    fn new(pool_config: PoolConfig) -> PoolState {
+        PoolState {
+            selector: pool_config.connection_selection().build(),
+            pool_config,
+            connections: Vec::new(),
+            waiters: VecDeque::new(),
+            recovering: false,
+            closing: false,
+            connect_backoff: None,
+            next_connect_at: None,
+            counters: PoolCounters::default(),
+        }
+    }
+
+    /// Whether a connection can serve one more channel, idle channels of another
+    /// profile being closed to make room when needed.
+    fn can_take_channel(&self, pooled_connection: &PooledConnection) -> bool {
+        pooled_connection.is_connected()
+            && !pooled_connection.is_expired(self.pool_config.max_connection_lifetime())
+            && (!pooled_connection.idle_channels.is_empty()
+                || pooled_connection.has_room(self.pool_config.max_channels_per_connection()))
+    }
+
+    /// Reserves a channel slot on the connection picked by the selector among those
+    /// with room for it, reusing one of its idle channels with the same profile if any.
+    fn try_reserve_channel(&mut self, profile: Option<&str>) -> Option<Reservation> {
+        self.discard_unusable_idle_channels();
+
+        let candidate_indexes: Vec<usize> = (0..self.connections.len())
+            .filter(|index| self.can_take_channel(&self.connections[*index]))
+            .collect();
+
+        if candidate_indexes.is_empty() {
+            return None;
+        }
+
+        let candidates: Vec<ConnectionCandidate> = candidate_indexes
+            .iter()
+            .map(|index| {
+                let pooled_connection = &self.connections[*index];
+
+                ConnectionCandidate::new(
+                    &pooled_connection.connection.id,
+                    pooled_connection.leased_channels.len() + pooled_connection.pending_channels,
+                )
+            })
+            .collect();
+
+        let selected_index = self.selector.select(&candidates);
+        let max_channels_per_connection = self.pool_config.max_channels_per_connection();
+        let pooled_connection = match candidate_indexes.get(selected_index) {
+            Some(index) => &mut self.connections[*index],
+            None => return None,
+        };
+
+        let idle_channel = match pooled_connection
+            .idle_channels
+            .iter()
+            .rposition(|idle_channel| idle_channel.profile.as_deref() == profile)
+        {
+            Some(index) => Some(pooled_connection.idle_channels.remove(index)),
+            None => None,
+        };
+
+        pooled_connection.last_used = Instant::now();
+
+        match &idle_channel {
+            Some(idle_channel) => {
+                pooled_connection
+                    .leased_channels
+                    .insert(idle_channel.channel.id(), idle_channel.channel.clone());
+            }
+            None => {
+                // the oldest idle channel of another profile gives its slot away
+                if !pooled_connection.has_room(max_channels_per_connection) {
+                    let evicted_channel = pooled_connection.idle_channels.remove(0);
+                    pooled_connection.full = false;
+
+                    close_channel(evicted_channel.channel);
+                }
+
+                pooled_connection.pending_channels += 1;
+            }
+        }
+
+        Some(Reservation {
+            connection: pooled_connection.connection.clone(),
+            idle_channel,
+        })
+    }
+
+    /// Swaps a reserved idle channel found unusable for a channel to be created in
+    /// its slot.
+    fn discard_reserved_channel(&mut self, connection_id: &str, channel_id: ChannelId) -> bool {
+        match self.get_connection_mut(connection_id) {
+            Some(pooled_connection) => {
+                pooled_connection.leased_channels.remove(&channel_id);
+                pooled_connection.pending_channels += 1;
+                self.counters.discarded_channels += 1;
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn cancel_reservation(&mut self, reservation: Reservation) {
+        match reservation.idle_channel {
+            Some(idle_channel) => {
+                if let Some(pooled_connection) = self.get_connection_mut(&reservation.connection.id)
+                {
+                    pooled_connection
+                        .leased_channels
+                        .remove(&idle_channel.channel.id());
+                    pooled_connection.idle_channels.push(idle_channel);
+                }
+            }
+            None => self.release_pending_channel(&reservation.connection.id, false),
+        }
+    }
+
+    fn has_waiters(&mut self) -> bool {
+        self.waiters.retain(|waiter| !waiter.sender.is_closed());
+
+        !self.waiters.is_empty()
+    }
+
+    /// Hands the available channel slots over to the waiters, in arrival order.
+    fn dispatch_waiters(&mut self) {
+        while self.has_waiters() {
+            let profile = self
+                .waiters
+                .front()
+                .and_then(|waiter| waiter.profile.clone());
+
+            let reservation = match self.try_reserve_channel(profile.as_deref()) {
+                Some(reservation) => reservation,
+                None => return,
+            };
+
+            if let Some(waiter) = self.waiters.pop_front() {
+                if let Err(reservation) = waiter.sender.send(reservation) {
+                    self.cancel_reservation(reservation);
+                }
+            }
+        }
+    }
+
+    fn release_pending_channel(&mut self, connection_id: &str, full: bool) {
+        if let Some(pooled_connection) = self.get_connection_mut(connection_id) {
+            pooled_connection.pending_channels -= 1;
+            pooled_connection.last_used = Instant::now();
+
+            if full {
+                pooled_connection.full = true;
+                self.counters.limit_hits += 1;
+            }
+        }
+    }
+
+    /// Takes a leased channel back, keeping it as idle when it can be reused.
+    ///
+    /// Returns the channel when it has to be closed instead.
+    fn release_channel(
+        &mut self,
+        connection_id: &str,
+        channel: Channel,
+        profile: Option<String>,
+        reusable: bool,
+    ) -> Option<Channel> {
+        let max_idle_channels_per_connection = self.pool_config.max_idle_channels_per_connection();
+
+        let pooled_connection = match self.get_connection_mut(connection_id) {
+            Some(pooled_connection) => pooled_connection,
+            None => return Some(channel),
+        };
+
+        // a channel closed by the broker was evicted already, and lapin may have
+        // handed its id out again since
+        let evicted = !channel.status().connected()
+            && pooled_connection
+                .leased_channels
+                .get(&channel.id())
+                .is_some_and(|leased_channel| leased_channel.status().connected());
+
+        if !evicted {
+            pooled_connection.leased_channels.remove(&channel.id());
+        }
+
+        pooled_connection.last_used = Instant::now();
+
+        let has_idle_room = match max_idle_channels_per_connection {
+            Some(max_idle_channels) => {
+                pooled_connection.idle_channels.len() < max_idle_channels as usize
+            }
+            None => true,
+        };
+
+        if reusable
+            && has_idle_room
+            && channel.status().connected()
+            && pooled_connection.is_connected()
+        {
+            pooled_connection
+                .idle_channels
+                .push(IdleChannel { channel, profile });
+
+            None
+        } else {
+            pooled_connection.full = false;
+
+            Some(channel)
+        }
+    }
+
+    /// Forgets a channel closed by the broker, returning whether it was still known.
+    fn evict_channel(&mut self, connection_id: &str, channel_id: ChannelId) -> bool {
+        let pooled_connection = match self.get_connection_mut(connection_id) {
+            Some(pooled_connection) => pooled_connection,
+            None => return false,
+        };
+
+        let leased = pooled_connection
+            .leased_channels
+            .get(&channel_id)
+            .is_some_and(|channel| !channel.status().connected());
+
+        if leased {
+            pooled_connection.leased_channels.remove(&channel_id);
+        }
+
+        let idle_channel_count = pooled_connection.idle_channels.len();
+        pooled_connection.idle_channels.retain(|idle_channel| {
+            idle_channel.channel.id() != channel_id || idle_channel.channel.status().connected()
+        });
+
+        leased || pooled_connection.idle_channels.len() != idle_channel_count
+    }
+
+    fn get_connection_mut(&mut self, connection_id: &str) -> Option<&mut PooledConnection> {
+        self.connections
+            .iter_mut()
+            .find(|pooled_connection| pooled_connection.connection.id == connection_id)
+    }
+
+    fn remove_connection(&mut self, connection_id: &str) -> bool {
+        let connection_count = self.connections.len();
+
+        self.connections
+            .retain(|pooled_connection| pooled_connection.connection.id != connection_id);
+
+        self.connections.len() != connection_count
+    }
+
+    /// Connections counted against the pool limit, leaving out the expired ones which
+    /// are only kept until their last channel is released.
+    fn active_connection_count(&self) -> usize {
+        let max_lifetime = self.pool_config.max_connection_lifetime();
+
+        self.connections
+            .iter()
+            .filter(|pooled_connection| !pooled_connection.is_expired(max_lifetime))
+            .count()
+    }
+
+    /// Removes the expired connections left without any leased or pending channel.
+    fn take_expired_connections(&mut self) -> Vec<Arc<WrappedConnection>> {
+        let max_lifetime = self.pool_config.max_connection_lifetime();
+        let mut expired_connections = Vec::new();
+
+        self.connections.retain(|pooled_connection| {
+            let expired = pooled_connection.is_expired(max_lifetime)
+                && pooled_connection.leased_channels.is_empty()
+                && pooled_connection.pending_channels == 0;
+
+            if expired {
+                expired_connections.push(pooled_connection.connection.clone());
+            }
+
+            !expired
+        });
+
+        self.counters.rotated_connections += expired_connections.len() as u64;
+
+        expired_connections
+    }
+
+    /// Drops the idle channels that can no longer be leased, because either they or
+    /// their connection got closed or errored, counting them as discarded.
+    fn discard_unusable_idle_channels(&mut self) {
+        for pooled_connection in self.connections.iter_mut() {
+            let connected = pooled_connection.is_connected();
+            let idle_channel_count = pooled_connection.idle_channels.len();
+
+            pooled_connection
+                .idle_channels
+                .retain(|idle_channel| connected && idle_channel.channel.status().connected());
+
+            self.counters.discarded_channels +=
+                (idle_channel_count - pooled_connection.idle_channels.len()) as u64;
+        }
+    }
+
+    /// Removes the connections without leased channels for at least `idle_timeout`,
+    /// least recently used first, as long as `min_connections` are kept.
+    ///
+    /// The expired ones among them are counted as rotated.
+    fn take_idle_connections(
+        &mut self,
+        idle_timeout: Duration,
+        min_connections: usize,
+    ) -> Vec<Arc<WrappedConnection>> {
+        let max_lifetime = self.pool_config.max_connection_lifetime();
+        let mut idle_connections: Vec<(Instant, String)> = self
+            .connections
+            .iter()
+            .filter(|pooled_connection| pooled_connection.is_idle_for(idle_timeout))
+            .map(|pooled_connection| {
+                (
+                    pooled_connection.last_used,
+                    pooled_connection.connection.id.clone(),
+                )
+            })
+            .collect();
+
+        idle_connections.sort();
+
+        let removable_count = self.connections.len().saturating_sub(min_connections);
+        let mut removed_connections = Vec::new();
+
+        for (_, connection_id) in idle_connections.into_iter().take(removable_count) {
+            if let Some(index) = self
+                .connections
+                .iter()
+                .position(|pooled_connection| pooled_connection.connection.id == connection_id)
+            {
+                let pooled_connection = self.connections.remove(index);
+
+                if pooled_connection.is_expired(max_lifetime) {
+                    self.counters.rotated_connections += 1;
+                }
+
+                removed_connections.push(pooled_connection.connection);
+            }
+        }
+
+        removed_connections
+    }
+
+    fn stats(&self, pool_key: &PoolKey) -> PoolStats {
+        PoolStats {
+            pool: pool_key.name(),
+            connections: self
+                .connections
+                .iter()
+                .map(PooledConnection::stats)
+                .collect(),
+            waiters: self
+                .waiters
+                .iter()
+                .filter(|waiter| !waiter.sender.is_closed())
+                .count(),
+            channels_created: self.counters.channels_created,
+            connect_failures: self.counters.connect_failures,
+            limit_hits: self.counters.limit_hits,
+            dead_connections: self.counters.dead_connections,
+            heartbeat_timeouts: self.counters.heartbeat_timeouts,
+            discarded_channels: self.counters.discarded_channels,
+            rotated_connections: self.counters.rotated_connections,
+        }
+    }
+
+    /// Records which connections got blocked or unblocked since the last poll.
+    fn poll_blocked_connections(&mut self, pool_key: &PoolKey) -> Vec<WrapperEvent> {
+        let mut events = Vec::new();
+
+        for pooled_connection in self.connections.iter_mut() {
+            let blocked = pooled_connection.connection.connection.status().blocked();
+
+            if blocked == pooled_connection.blocked {
+                continue;
+            }
+
+            pooled_connection.blocked = blocked;
+
+            let pool = pool_key.name();
+            let connection_id = pooled_connection.connection.id.clone();
+            let node = pooled_connection.connection.node.clone();
+
+            events.push(if blocked {
+                WrapperEvent::ConnectionBlocked {
+                    pool,
+                    connection_id,
+                    node,
+                }
+            } else {
+                WrapperEvent::ConnectionUnblocked {
+                    pool,
+                    connection_id,
+                    node,
+                }
+            });
+        }
+
+        events
+    }
+
+    /// Backs the connections opened on demand off after a failed one.
+    fn on_connect_failure(&mut self, backoff_config: &BackoffConfig) {
+        let backoff = self
+            .connect_backoff
+            .get_or_insert_with(|| Backoff::new(backoff_config.clone()));
+
+        self.next_connect_at = Some(Instant::now() + backoff.next_delay());
+        self.counters.connect_failures += 1;
+    }
+
+    fn on_connect_success(&mut self) {
+        self.connect_backoff = None;
+        self.next_connect_at = None;
+    }
+
+    /// Removes the connections the broker stayed silent on for at least `max_missed`
+    /// heartbeat intervals, along with how many intervals it did.
+    fn take_silent_connections(&mut self, max_missed: u32) -> Vec<(Arc<WrappedConnection>, u32)> {
+        let mut silent_connections = Vec::new();
+
+        self.connections.retain(|pooled_connection| {
+            let missed_heartbeats = pooled_connection.connection.missed_heartbeats();
+            let silent = missed_heartbeats >= max_missed;
+
+            if silent {
+                silent_connections.push((pooled_connection.connection.clone(), missed_heartbeats));
+            }
+
+            !silent
+        });
+
+        self.counters.dead_connections += silent_connections.len() as u64;
+        self.counters.heartbeat_timeouts += silent_connections.len() as u64;
+
+        silent_connections
+    }
+
+    /// Removes the connections which are closed or errored, returning how many were.
+    fn evict_dead_connections(&mut self) -> usize {
+        let connection_count = self.connections.len();
+
+        self.connections
+            .retain(|pooled_connection| pooled_connection.is_connected());
+
+        connection_count - self.connections.len()
+    }
+}
+
+/// Identifies one of the pools of the wrapper, none of them sharing connections.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum PoolKey {
+    Shared,
+    Dedicated(ChannelPurpose),
+    Group(String),
+}
+
+impl PoolKey {
+    fn name(&self) -> String {
+        match self {
+            PoolKey::Shared => "shared".to_string(),
+            PoolKey::Dedicated(ChannelPurpose::Publish) => "publish".to_string(),
+            PoolKey::Dedicated(ChannelPurpose::Consume) => "consume".to_string(),
+            PoolKey::Group(group) => format!("group:{}", group),
+        }
+    }
+}
+
+struct Pool {
+    state: Mutex<PoolState>,
+    // Serializes the opening of new connections without blocking the callers
+    // that can be served by the already existing ones.
+    connect_lock: tokio::sync::Mutex<()>,
+    // Number of connections opened so far, used as `{index}` in their names.
+    opened_connections: AtomicUsize,
+}
+
+impl Pool {
+    fn new(pool_config: PoolConfig) -> Pool {
+        Pool {
+            state: Mutex::new(PoolState::new(pool_config)),
+            connect_lock: tokio::sync::Mutex::new(()),
+            opened_connections: AtomicUsize::new(0),
+        }
+    }
+
+    fn lock_state(&self) -> Result<MutexGuard<'_, PoolState>, Error> {
+        match self.state.lock() {
+            Ok(state) => Ok(state),
+            Err(_) => Err(Error::new(
+                ErrorKind::StateUpdateFailure,
+                "failed to lock the state of the connections",
+            )),
+        }
+    }
+}
+
+pub(crate) struct AmqpWrapperInner {
+    pools: HashMap<PoolKey, Pool>,
+    connect_config: AmqpConnectConfig,
+    // Index of the broker URI the last connection was opened to.
+    last_connected_endpoint: AtomicUsize,
+    janitor_started: AtomicBool,
+    watcher_started: AtomicBool,
+    events: broadcast::Sender<WrapperEvent>,
+    circuit_breaker: Option<Mutex<CircuitBreaker>>,
+    connect_rate_limiter: Option<Mutex<ConnectRateLimiter>>,
+}
+
+impl AmqpWrapperInner {
+    fn pool(&self, pool_key: &PoolKey) -> Result<&Pool, Error> {
+        match self.pools.get(pool_key) {
+            Some(pool) => Ok(pool),
+            None => Err(Error::new(
+                ErrorKind::InternalFailure,
+                format!("no pool found for {:?}", pool_key),
+            )),
+        }
+    }
+
+    fn lock_state(&self, pool_key: &PoolKey) -> Result<MutexGuard<'_, PoolState>, Error> {
+        self.pool(pool_key)?.lock_state()
+    }
+
+    /// Key of the pool serving the channels of a purpose, the shared one unless
+    /// the purpose has a dedicated pool.
+    fn pool_key_for(&self, purpose: ChannelPurpose) -> PoolKey {
+        let pool_key = PoolKey::Dedicated(purpose);
+
+        if self.pools.contains_key(&pool_key) {
+            pool_key
+        } else {
+            PoolKey::Shared
+        }
+    }
+
+    /// Client properties of a new connection of the pool, identifying this crate
+    /// and, when a template is configured, naming the connection.
+    fn connection_options(
+        &self,
+        pool_key: &PoolKey,
+        connection_id: &str,
+    ) -> Result<ConnectionProperties, Error> {
+        let mut options = self.connect_config.cloned_options();
+
+        // lapin fills `platform` in by itself, and `product`/`version` with its
+        // own name unless they are already set.
+        for (key, value) in [
+            ("product", env!("CARGO_PKG_NAME")),
+            ("version", env!("CARGO_PKG_VERSION")),
+        ] {
+            if !options.client_properties.contains_key(key) {
+                options
+                    .client_properties
+                    .insert(key.into(), AMQPValue::LongString(value.into()));
+            }
+        }
+
+        if let Some(template) = self.connect_config.connection_name() {
+            let index = self
+                .pool(pool_key)?
+                .opened_connections
+                .fetch_add(1, Ordering::Relaxed);
+            let hostname = hostname();
+            let context = ConnectionNameContext {
+                service: self.connect_config.service_name().unwrap_or("unknown"),
+                hostname: &hostname,
+                pool: &pool_key.name(),
+                index,
+                connection_id,
+            };
+
+            options =
+                options.with_connection_name(render_connection_name(template, &context).into());
+        }
+
+        Ok(options)
+    }
+
+    fn lock_circuit_breaker(&self) -> Result<Option<MutexGuard<'_, CircuitBreaker>>, Error> {
+        match &self.circuit_breaker {
+            Some(circuit_breaker) => match circuit_breaker.lock() {
+                Ok(circuit_breaker) => Ok(Some(circuit_breaker)),
+                Err(_) => Err(Error::new(
+                    ErrorKind::StateUpdateFailure,
+                    "failed to lock the state of the circuit breaker",
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn circuit_state(&self) -> Result<CircuitState, Error> {
+        Ok(match self.lock_circuit_breaker()? {
+            Some(circuit_breaker) => circuit_breaker.state(),
+            None => CircuitState::Closed,
+        })
+    }
+
+    /// Fails fast while the broker is deemed down.
+    fn check_circuit(&self) -> Result<(), Error> {
+        match self.lock_circuit_breaker()? {
+            Some(circuit_breaker) if circuit_breaker.is_open(Instant::now()) => {
+                Err(circuit_open_error())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Waits until the connection rate limit lets another attempt through.
+    async fn wait_for_connect_turn(&self) -> Result<(), Error> {
+        let connect_rate_limiter = match &self.connect_rate_limiter {
+            Some(connect_rate_limiter) => connect_rate_limiter,
+            None => return Ok(()),
+        };
+
+        loop {
+            let wait = match connect_rate_limiter.lock() {
+                Ok(mut connect_rate_limiter) => connect_rate_limiter.try_acquire(Instant::now()),
+                Err(_) => {
+                    return Err(Error::new(
+                        ErrorKind::StateUpdateFailure,
+                        "failed to lock the state of the connection rate limit",
+                    ))
+                }
+            };
+
+            match wait {
+                Ok(()) => return Ok(()),
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+
+    /// Connects to the first reachable broker URI, in the configured endpoint order,
+    /// within the connection rate limit and as long as the circuit breaker allows it.
+    async fn connect(&self, pool_key: &PoolKey) -> Result<WrappedConnection, Error> {
+        self.check_circuit()?;
+        self.wait_for_connect_turn().await?;
+
+        if let Some(mut circuit_breaker) = self.lock_circuit_breaker()? {
+            if !circuit_breaker.try_acquire(Instant::now()) {
+                return Err(circuit_open_error());
+            }
+        }
+
+        let result = self.connect_to_endpoints(pool_key).await;
+
+        if let Some(mut circuit_breaker) = self.lock_circuit_breaker()? {
+            match &result {
+                Ok(_) => circuit_breaker.on_success(),
+                Err(_) => circuit_breaker.on_failure(Instant::now()),
+            }
+        }
+
+        result
+    }
+
+    async fn connect_to_endpoints(&self, pool_key: &PoolKey) -> Result<WrappedConnection, Error> {
+        let connection_id = uuid::Uuid::new_v4().to_string();
+        let mut options = self.connection_options(pool_key, &connection_id)?;
+        // the one lapin falls back to
+        let reactor = options
+            .reactor
+            .take()
+            .unwrap_or_else(|| Arc::new(async_reactor_trait::AsyncIo));
+        let uris = self.connect_config.uris();
+        let endpoint_indexes = self.connect_config.endpoint_order().endpoint_indexes(
+            uris.len(),
+            self.last_connected_endpoint.load(Ordering::Relaxed),
+        );
+        let mut failures = Vec::new();
+
+        for index in endpoint_indexes {
+            let uri = uris[index].as_str();
+            let node = node_of(uri);
+
+            let mut uri = match uri.parse::<AMQPUri>() {
+                Ok(uri) => uri,
+                Err(error) => {
+                    failures.push(format!("{}: invalid uri: {}", node, error));
+                    continue;
+                }
+            };
+
+            if let Some(heartbeat_config) = self.connect_config.heartbeat_config() {
+                uri.query.heartbeat = Some(heartbeat_config.interval_secs());
+            }
+
+            let read_clock = Arc::new(ReadClock::new());
+            let mut options = options.clone();
+            options.reactor = Some(Arc::new(ReadTrackingReactor::new(
+                reactor.clone(),
+                read_clock.clone(),
+            )));
+
+            match Connection::connect_uri_with_config(
+                uri,
+                options,
+                self.connect_config.cloned_owned_tls_config(),
+            )
+            .await
+            {
+                Ok(connection) => {
+                    self.last_connected_endpoint.store(index, Ordering::Relaxed);
+
+                    return Ok(WrappedConnection::new(
+                        connection_id,
+                        connection,
+                        node,
+                        read_clock,
+                    ));
+                }
+                Err(error) => failures.push(format!("{}: {}", node, error)),
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::InternalFailure,
+            format!("failed to connect: {}", failures.join("; ")),
+        ))
+    }
+
+    async fn open_connection(
+        self: &Arc<Self>,
+        pool_key: &PoolKey,
+    ) -> Result<Arc<WrappedConnection>, Error> {
+        let connection = match self.connect(pool_key).await {
+            Ok(connection) => connection,
+            Err(error) => {
+                if error.kind != ErrorKind::CircuitOpen {
+                    if let Ok(mut state) = self.lock_state(pool_key) {
+                        state.on_connect_failure(self.connect_config.recovery_config().backoff());
+                    }
+                }
+
+                return Err(error);
+            }
+        };
+
+        if let Ok(mut state) = self.lock_state(pool_key) {
+            state.on_connect_success();
+        }
+
+        // lapin may report the error from a thread outside of the Tokio runtime
+        let runtime = Handle::current();
+        let wrapper = Arc::downgrade(self);
+        let pool_key = pool_key.clone();
+        let connection_id = connection.id.clone();
+
+        connection.connection.on_error(move |error| {
+            if let Some(wrapper) = wrapper.upgrade() {
+                wrapper.on_connection_error(&pool_key, &connection_id, error, &runtime);
+            }
+        });
+
+        self.start_janitor();
+        self.start_watcher();
+
+        Ok(Arc::new(connection))
+    }
+
+    fn start_janitor(self: &Arc<Self>) {
+        let janitor_config = match self.connect_config.janitor_config() {
+            Some(janitor_config) => janitor_config.clone(),
+            None => return,
+        };
+
+        if self.janitor_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        tokio::spawn(janitor(Arc::downgrade(self), janitor_config));
+    }
+
+    fn start_watcher(self: &Arc<Self>) {
+        if self.watcher_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let mut poll_interval = self.connect_config.blocked_config().poll_interval();
+
+        // often enough to tell a silent connection within one heartbeat interval
+        if let Some(heartbeat_config) = self.connect_config.heartbeat_config() {
+            poll_interval = poll_interval.min(heartbeat_config.interval());
+        }
+
+        tokio::spawn(watch_connections(Arc::downgrade(self), poll_interval));
+    }
+
+    /// Opens connections until the pool holds at least `min_connections` which have not
+    /// expired yet.
+    async fn open_missing_connections(
+        self: &Arc<Self>,
+        pool_key: &PoolKey,
+        min_connections: usize,
+    ) -> Result<(), Error> {
+        let pool = self.pool(pool_key)?;
+
+        loop {
+            let _connect_guard = pool.connect_lock.lock().await;
+
+            {
+                let state = pool.lock_state()?;
+
+                if state.closing || state.active_connection_count() >= min_connections {
+                    return Ok(());
+                }
+            }
+
+            let connection = self.open_connection(pool_key).await?;
+            self.push_connection(pool_key, PooledConnection::new(connection))
+                .await?;
+        }
+    }
+
+    /// Evicts a connection lapin deemed dead, e.g. after missing heartbeats, and
+    /// reports it.
+    ///
+    /// Connections silent for fewer heartbeat intervals than lapin tolerates are
+    /// evicted beforehand by `watch_connections`, see `HeartbeatConfig`.
+    fn on_connection_error(
+        self: &Arc<Self>,
+        pool_key: &PoolKey,
+        connection_id: &str,
+        error: lapin::Error,
+        runtime: &Handle,
+    ) {
+        // lapin never deems two heartbeat errors equal
+        let heartbeats_missed = matches!(error, lapin::Error::MissingHeartbeatError);
+
+        let dead_connection = match self.lock_state(pool_key) {
+            Ok(mut state) => match state.get_connection_mut(connection_id) {
+                Some(pooled_connection) => {
+                    let dead_connection = (
+                        pooled_connection.connection.node.clone(),
+                        pooled_connection.connection.missed_heartbeats(),
+                    );
+
+                    state.counters.dead_connections += 1;
+
+                    if heartbeats_missed {
+                        state.counters.heartbeat_timeouts += 1;
+                    }
+
+                    Some(dead_connection)
+                }
+                None => None,
+            },
+            Err(_) => None,
+        };
+
+        self.evict_connection(pool_key, connection_id, runtime);
+
+        if let Some((node, missed_heartbeats)) = dead_connection {
+            // nobody listening is fine
+            let _ = self.events.send(WrapperEvent::ConnectionDead {
+                pool: pool_key.name(),
+                connection_id: connection_id.to_string(),
+                node,
+                reason: error.to_string(),
+                missed_heartbeats,
+            });
+        }
+    }
+
+    fn evict_connection(
+        self: &Arc<Self>,
+        pool_key: &PoolKey,
+        connection_id: &str,
+        runtime: &Handle,
+    ) {
+        let evicted = match self.lock_state(pool_key) {
+            Ok(mut state) => state.remove_connection(connection_id),
+            Err(_) => return,
+        };
+
+        if evicted {
+            self.start_recovery(pool_key, runtime);
+        }
+    }
+
+    fn start_recovery(self: &Arc<Self>, pool_key: &PoolKey, runtime: &Handle) {
+        if !self.connect_config.recovery_config().enabled() {
+            return;
+        }
+
+        match self.lock_state(pool_key) {
+            Ok(mut state) => {
+                if state.recovering || state.closing {
+                    return;
+                }
+
+                state.recovering = true;
+            }
+            Err(_) => return,
+        }
+
+        runtime.spawn(recover(Arc::downgrade(self), pool_key.clone()));
+    }
+
+    /// Adds a newly opened connection to the pool, handing its channel slots over to
+    /// the callers already waiting for one, unless the wrapper got closed
+    /// in the meantime.
+    async fn push_connection(
+        &self,
+        pool_key: &PoolKey,
+        pooled_connection: PooledConnection,
+    ) -> Result<(), Error> {
+        {
+            let mut state = self.lock_state(pool_key)?;
+
+            if !state.closing {
+                state.connections.push(pooled_connection);
+                state.dispatch_waiters();
+
+                return Ok(());
+            }
+        }
+
+        close_connection(&pooled_connection.connection, "wrapper closed").await;
+
+        Err(closed_error())
+    }
+
+    /// Evicts the channel as soon as the broker closes it, reporting why.
+    fn watch_channel(self: &Arc<Self>, pool_key: &PoolKey, connection_id: &str, channel: &Channel) {
+        let wrapper = Arc::downgrade(self);
+        let pool_key = pool_key.clone();
+        let connection_id = connection_id.to_string();
+        let channel_id = channel.id();
+
+        // only called by lapin when the broker closes the channel with an error
+        channel.on_error(move |error| {
+            if let Some(wrapper) = wrapper.upgrade() {
+                wrapper.evict_channel(&pool_key, &connection_id, channel_id, error);
+            }
+        });
+    }
+
+    fn evict_channel(
+        &self,
+        pool_key: &PoolKey,
+        connection_id: &str,
+        channel_id: ChannelId,
+        error: lapin::Error,
+    ) {
+        match self.lock_state(pool_key) {
+            Ok(mut state) => {
+                if !state.evict_channel(connection_id, channel_id) {
+                    return;
+                }
+
+                state.dispatch_waiters();
+            }
+            Err(_) => return,
+        }
+
+        if let lapin::Error::ProtocolError(error) = error {
+            // nobody listening is fine
+            let _ = self.events.send(WrapperEvent::ChannelClosed {
+                pool: pool_key.name(),
+                connection_id: connection_id.to_string(),
+                channel_id,
+                reply_code: error.get_id(),
+                reply_text: error.get_message().to_string(),
+            });
+        }
+    }
+
+    /// Takes back a leased channel, either keeping it for reuse or closing it, and
+    /// hands the freed slot over to the first waiter if any.
+    pub(crate) fn release_channel(
+        &self,
+        pool_key: &PoolKey,
+        connection_id: &str,
+        channel: Channel,
+        profile: Option<String>,
+        reusable: bool,
+    ) {
+        let (channel_to_close, expired_connections) = match self.lock_state(pool_key) {
+            Ok(mut state) => {
+                let channel_to_close =
+                    state.release_channel(connection_id, channel, profile, reusable);
+
+                state.dispatch_waiters();
+
+                (channel_to_close, state.take_expired_connections())
+            }
+            Err(_) => (Some(channel), Vec::new()),
+        };
+
+        if let Some(channel) = channel_to_close {
+            close_channel(channel);
+        }
+
+        // without a runtime, lapin still closes the connection once it is dropped
+        if let Ok(runtime) = Handle::try_current() {
+            for expired_connection in expired_connections {
+                runtime.spawn(async move {
+                    close_connection(&expired_connection, "connection rotated").await;
+                });
+            }
+        }
+    }
+}
+
+/// Closes a channel on the broker without waiting for its reply.
+fn close_channel(channel: Channel) {
+    if !channel.status().connected() {
+        return;
+    }
+
+    // without a runtime, lapin still closes the channel once its last clone is dropped
+    if let Ok(runtime) = Handle::try_current() {
+        runtime.spawn(async move {
+            let _ = channel.close(REPLY_SUCCESS, "OK").await;
+        });
+    }
+}
+
+fn validate_warm_up_config(
+    warm_up_config: &WarmUpConfig,
+    pool_config: &PoolConfig,
+) -> Result<(), Error> {
+    if let Some(max_connections) = pool_config.max_connections() {
+        if warm_up_config.connections() > max_connections {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                "cannot warm up more connections than the pool allows",
+            ));
+        }
+    }
+
+    let max_channels = pool_config
+        .max_channels_per_connection()
+        .into_iter()
+        .chain(pool_config.max_idle_channels_per_connection())
+        .min();
+
+    if let Some(max_channels) = max_channels {
+        if warm_up_config.channels_per_connection() > max_channels {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                "cannot warm up more channels per connection than the pool keeps idle",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Identifies the broker node of a URI without exposing its credentials.
+fn node_of(uri: &str) -> String {
+    match uri.parse::<AMQPUri>() {
+        Ok(uri) => format!("{}:{}", uri.authority.host, uri.authority.port),
+        Err(_) => "invalid uri".to_string(),
+    }
+}
+
+/// Sets a newly created channel up as described by its profile.
+async fn apply_channel_profile(
+    channel: &Channel,
+    channel_profile: &ChannelProfile,
+) -> Result<(), lapin::Error> {
+    if let Some(prefetch_count) = channel_profile.prefetch_count() {
+        let options = BasicQosOptions {
+            global: channel_profile.global_qos(),
+        };
+
+        channel.basic_qos(prefetch_count, options).await?;
+    }
+
+    if channel_profile.confirm() {
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await?;
+    }
+
+    if channel_profile.transactional() {
+        channel.tx_select().await?;
+    }
+
+    Ok(())
+}
+
+/// Settings of every pool of the wrapper, the shared one and the dedicated ones.
+fn pool_configs(connect_config: &AmqpConnectConfig) -> HashMap<PoolKey, PoolConfig> {
+    let mut pool_configs = HashMap::new();
+
+    pool_configs.insert(PoolKey::Shared, connect_config.pool_config().clone());
+
+    for purpose in [ChannelPurpose::Publish, ChannelPurpose::Consume] {
+        if let Some(pool_config) = connect_config.dedicated_pool_config(purpose) {
+            pool_configs.insert(PoolKey::Dedicated(purpose), pool_config.clone());
+        }
+    }
+
+    for (group, pool_config) in connect_config.group_pool_configs() {
+        pool_configs.insert(PoolKey::Group(group.clone()), pool_config.clone());
+    }
+
+    pool_configs
+}
+
+fn closed_error() -> Error {
+    Error::new(ErrorKind::Closed, "the wrapper has been closed")
+}
+
+fn circuit_open_error() -> Error {
+    Error::new(
+        ErrorKind::CircuitOpen,
+        "the circuit breaker is open, the broker is deemed down",
+    )
+}
+
+/// Waits for the publisher confirms still outstanding on a channel, then closes it.
+async fn close_channel_gracefully(
+    channel: &Channel,
+    deadline: tokio::time::Instant,
+) -> Result<(), String> {
+    if !channel.status().connected() {
+        return Ok(());
+    }
+
+    if channel.status().confirm() {
+        match tokio::time::timeout_at(deadline, channel.wait_for_confirms()).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(error)) => {
+                return Err(format!("failed to wait for publisher confirms: {}", error))
+            }
+            Err(_) => return Err("publisher confirms still pending".to_string()),
+        }
+    }
+
+    match tokio::time::timeout_at(deadline, channel.close(REPLY_SUCCESS, "OK")).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(error)) => Err(format!("failed to close channel: {}", error)),
+        Err(_) => Err("channel did not close in time".to_string()),
+    }
+}
+
+/// Closes a connection on the broker, along with all of its channels.
+async fn close_connection(connection: &WrappedConnection, reply_text: &str) {
+    if connection.connection.status().connected() {
+        let _ = connection.connection.close(REPLY_SUCCESS, reply_text).await;
+    }
+}
+
+/// Periodically drops the channels and connections which are closed, closes the
+/// connections idle for too long and opens the ones missing to stay warm.
+async fn janitor(wrapper: Weak<AmqpWrapperInner>, janitor_config: JanitorConfig) {
+    let mut interval = tokio::time::interval(janitor_config.interval());
+
+    loop {
+        interval.tick().await;
+
+        let wrapper = match wrapper.upgrade() {
+            Some(wrapper) => wrapper,
+            None => return,
+        };
+
+        let pool_keys: Vec<PoolKey> = wrapper.pools.keys().cloned().collect();
+
+        for pool_key in pool_keys.iter() {
+            if !tidy_up_pool(&wrapper, pool_key, &janitor_config).await {
+                return;
+            }
+        }
+    }
+}
+
+/// Runs one janitor pass over a pool, returning whether the janitor should go on.
+async fn tidy_up_pool(
+    wrapper: &Arc<AmqpWrapperInner>,
+    pool_key: &PoolKey,
+    janitor_config: &JanitorConfig,
+) -> bool {
+    let (evicted_connections, idle_connections) = match wrapper.lock_state(pool_key) {
+        Ok(mut state) => {
+            if state.closing {
+                return false;
+            }
+
+            let evicted_connections = state.evict_dead_connections();
+            state.discard_unusable_idle_channels();
+
+            let idle_connections = state.take_idle_connections(
+                janitor_config.idle_timeout(),
+                janitor_config.min_connections(),
+            );
+
+            (evicted_connections, idle_connections)
+        }
+        Err(_) => return false,
+    };
+
+    if evicted_connections > 0 {
+        wrapper.start_recovery(pool_key, &Handle::current());
+    }
+
+    for idle_connection in idle_connections {
+        close_connection(&idle_connection, "idle connection").await;
+    }
+
+    // failures are left to the recovery, the next tick tries again anyway
+    let _ = wrapper
+        .open_missing_connections(pool_key, janitor_config.min_connections())
+        .await;
+
+    true
+}
+
+/// Periodically polls the state of the connections, broadcasting its changes, and
+/// evicts the connections the broker stayed silent on for too long.
+async fn watch_connections(wrapper: Weak<AmqpWrapperInner>, poll_interval: Duration) {
+    let mut interval = tokio::time::interval(poll_interval);
+
+    loop {
+        interval.tick().await;
+
+        let wrapper = match wrapper.upgrade() {
+            Some(wrapper) => wrapper,
+            None => return,
+        };
+
+        let max_missed = wrapper
+            .connect_config
+            .heartbeat_config()
+            .map(|heartbeat_config| heartbeat_config.max_missed());
+
+        for (pool_key, pool) in wrapper.pools.iter() {
+            let (events, silent_connections, expired_connections) = match pool.lock_state() {
+                Ok(mut state) => {
+                    if state.closing {
+                        return;
+                    }
+
+                    let silent_connections = match max_missed {
+                        Some(max_missed) => state.take_silent_connections(max_missed),
+                        None => Vec::new(),
+                    };
+
+                    (
+                        state.poll_blocked_connections(pool_key),
+                        silent_connections,
+                        state.take_expired_connections(),
+                    )
+                }
+                Err(_) => return,
+            };
+
+            for event in events {
+                // nobody listening is fine
+                let _ = wrapper.events.send(event);
+            }
+
+            if !silent_connections.is_empty() {
+                wrapper.start_recovery(pool_key, &Handle::current());
+            }
 
-struct WrappedConnection {
-    pub id: String,
-    pub connection: Connection,
-}
+            for (silent_connection, missed_heartbeats) in silent_connections {
+                // nobody listening is fine
+                let _ = wrapper.events.send(WrapperEvent::ConnectionDead {
+                    pool: pool_key.name(),
+                    connection_id: silent_connection.id.clone(),
+                    node: silent_connection.node.clone(),
+                    reason: format!(
+                        "no frame received for {} heartbeat intervals",
+                        missed_heartbeats
+                    ),
+                    missed_heartbeats,
+                });
 
-impl WrappedConnection {
-    pub fn new(connection: Connection) -> WrappedConnection {
-        WrappedConnection {
-            id: uuid::Uuid::new_v4().to_string(),
-            connection,
+                // the peer may never answer the close of a half-open connection
+                tokio::spawn(async move {
+                    close_connection(&silent_connection, "missed heartbeats").await;
+                });
+            }
+
+            for expired_connection in expired_connections {
+                close_connection(&expired_connection, "connection rotated").await;
+            }
         }
     }
 }
 
-impl From<Connection> for WrappedConnection {
-    fn from(connection: Connection) -> Self {
-        WrappedConnection {
-            id: uuid::Uuid::new_v4().to_string(),
-            connection,
+/// Reconnects with an exponential backoff until the pool holds a working connection
+/// again, handing the new connection over to the callers waiting for a channel.
+async fn recover(wrapper: Weak<AmqpWrapperInner>, pool_key: PoolKey) {
+    let mut backoff = match wrapper.upgrade() {
+        Some(wrapper) => Backoff::new(wrapper.connect_config.recovery_config().backoff().clone()),
+        None => return,
+    };
+
+    loop {
+        tokio::time::sleep(backoff.next_delay()).await;
+
+        let wrapper = match wrapper.upgrade() {
+            Some(wrapper) => wrapper,
+            None => return,
+        };
+
+        let pool = match wrapper.pool(&pool_key) {
+            Ok(pool) => pool,
+            Err(_) => return,
+        };
+
+        let _connect_guard = pool.connect_lock.lock().await;
+
+        let recovered = match pool.lock_state() {
+            Ok(mut state) => {
+                if state.closing {
+                    return;
+                }
+
+                state.evict_dead_connections();
+                !state.connections.is_empty()
+            }
+            Err(_) => return,
+        };
+
+        if !recovered {
+            let connection = match wrapper.open_connection(&pool_key).await {
+                Ok(connection) => connection,
+                Err(_) => continue,
+            };
+
+            if wrapper
+                .push_connection(&pool_key, PooledConnection::new(connection))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+
+        if let Ok(mut state) = pool.lock_state() {
+            state.recovering = false;
+            state.dispatch_waiters();
         }
+
+        return;
     }
 }
 
 /// Wrapper around lapin.rs that works only with Tokio.
+///
+/// Cloning it is cheap: every clone shares the same connections and channels,
+/// so a single instance can be handed to as many tasks as needed.
+#[derive(Clone)]
 pub struct AmqpWrapper {
-    connections: Vec<WrappedConnection>,
-    channels_per_connection: HashMap<String, Vec<Weak<Channel>>>,
-    connect_config: AmqpConnectConfig,
+    inner: Arc<AmqpWrapperInner>,
 }
 
 impl AmqpWrapper {
     pub fn try_new(connect_config: AmqpConnectConfig) -> Result<AmqpWrapper, Error> {
+        connect_config.validate()?;
+
+        let pool_configs = pool_configs(&connect_config);
+
+        if let Some(janitor_config) = connect_config.janitor_config() {
+            for pool_config in pool_configs.values() {
+                if let Some(max_connections) = pool_config.max_connections() {
+                    if janitor_config.min_connections() > max_connections {
+                        return Err(Error::new(
+                            ErrorKind::ConfigFailure,
+                            "the janitor cannot keep more connections than the pool allows",
+                        ));
+                    }
+                }
+            }
+        }
+
+        let pools = pool_configs
+            .into_iter()
+            .map(|(pool_key, pool_config)| (pool_key, Pool::new(pool_config)))
+            .collect();
+
+        let circuit_breaker =
+            connect_config
+                .circuit_breaker_config()
+                .map(|circuit_breaker_config| {
+                    Mutex::new(CircuitBreaker::new(circuit_breaker_config.clone()))
+                });
+        let connect_rate_limiter =
+            connect_config
+                .connect_rate_limit_config()
+                .map(|connect_rate_limit_config| {
+                    Mutex::new(ConnectRateLimiter::new(connect_rate_limit_config.clone()))
+                });
+
         Ok(AmqpWrapper {
-            connections: Vec::new(),
-            channels_per_connection: HashMap::new(),
-            connect_config,
+            inner: Arc::new(AmqpWrapperInner {
+                pools,
+                connect_config,
+                last_connected_endpoint: AtomicUsize::new(0),
+                janitor_started: AtomicBool::new(false),
+                watcher_started: AtomicBool::new(false),
+                events: broadcast::channel(EVENTS_CAPACITY).0,
+                circuit_breaker,
+                connect_rate_limiter,
+            }),
         })
     }
 
-    async fn try_connect(&mut self) -> Result<&WrappedConnection, Error> {
-        let connection: WrappedConnection = match Connection::connect_with_config(
-            self.connect_config.uri(),
-            self.connect_config.cloned_options(),
-            self.connect_config.cloned_owned_tls_config(),
-        )
-        .await
-        {
-            Ok(connection) => connection.into(),
-            Err(error) => {
-                return Err(Error::new(
-                    ErrorKind::InternalFailure,
-                    format!("failed to connect: {}", error),
-                ));
+    /// Creates the wrapper and opens its warm-up connections and channels right away,
+    /// so an unreachable or misconfigured broker is reported before it is ever used.
+    pub async fn try_new_warmed_up(
+        connect_config: AmqpConnectConfig,
+    ) -> Result<AmqpWrapper, Error> {
+        for pool_config in pool_configs(&connect_config).values() {
+            validate_warm_up_config(connect_config.warm_up_config(), pool_config)?;
+        }
+
+        let amqp_wrapper = AmqpWrapper::try_new(connect_config)?;
+
+        // connections opened so far are closed along with the wrapper on failure
+        for pool_key in amqp_wrapper.inner.pools.keys() {
+            amqp_wrapper.warm_up(pool_key).await?;
+        }
+
+        Ok(amqp_wrapper)
+    }
+
+    async fn warm_up(&self, pool_key: &PoolKey) -> Result<(), Error> {
+        let warm_up_config = self.inner.connect_config.warm_up_config();
+        let connections = warm_up_config.connections();
+        let _connect_guard = self.inner.pool(pool_key)?.connect_lock.lock().await;
+
+        for index in 1..=connections {
+            let connection = match self.inner.open_connection(pool_key).await {
+                Ok(connection) => connection,
+                Err(error) => {
+                    return Err(Error::new(
+                        error.kind,
+                        format!(
+                            "failed to warm up connection {} of {}: {}",
+                            index, connections, error.message
+                        ),
+                    ))
+                }
+            };
+
+            let mut pooled_connection = PooledConnection::new(connection.clone());
+
+            for _ in 0..warm_up_config.channels_per_connection() {
+                match connection.connection.create_channel().await {
+                    Ok(channel) => {
+                        self.inner.watch_channel(pool_key, &connection.id, &channel);
+
+                        pooled_connection.idle_channels.push(IdleChannel {
+                            channel,
+                            profile: None,
+                        });
+                    }
+                    Err(error) => {
+                        return Err(Error::new(
+                            ErrorKind::InternalFailure,
+                            format!(
+                                "failed to warm up a channel on connection {} of {}: {}",
+                                index, connections, error
+                            ),
+                        ))
+                    }
+                }
+            }
+
+            self.inner
+                .push_connection(pool_key, pooled_connection)
+                .await?;
+
+            self.lock_state(pool_key)?.counters.channels_created +=
+                warm_up_config.channels_per_connection() as u64;
+        }
+
+        Ok(())
+    }
+
+    pub fn pool_config(&self) -> &PoolConfig {
+        self.inner.connect_config.pool_config()
+    }
+
+    /// Subscribes to the events of the wrapper, such as connections being blocked.
+    pub fn subscribe(&self) -> broadcast::Receiver<WrapperEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Ids of the connections currently blocked by the broker, across every pool.
+    pub fn blocked_connections(&self) -> Result<Vec<String>, Error> {
+        let mut blocked_connections = Vec::new();
+
+        for pool in self.inner.pools.values() {
+            let state = pool.lock_state()?;
+
+            blocked_connections.extend(
+                state
+                    .connections
+                    .iter()
+                    .filter(|pooled_connection| {
+                        pooled_connection.connection.connection.status().blocked()
+                    })
+                    .map(|pooled_connection| pooled_connection.connection.id.clone()),
+            );
+        }
+
+        Ok(blocked_connections)
+    }
+
+    /// Takes a snapshot of every pool, shared pool first, to be logged or exposed.
+    pub fn stats(&self) -> Result<WrapperStats, Error> {
+        let mut pool_keys: Vec<&PoolKey> = self.inner.pools.keys().collect();
+        pool_keys.sort_by_key(|pool_key| (*pool_key != &PoolKey::Shared, pool_key.name()));
+
+        let mut pools = Vec::new();
+
+        for pool_key in pool_keys {
+            pools.push(self.lock_state(pool_key)?.stats(pool_key));
+        }
+
+        Ok(WrapperStats {
+            pools,
+            circuit_breaker: self.inner.circuit_state()?,
+        })
+    }
+
+    fn lock_state(&self, pool_key: &PoolKey) -> Result<MutexGuard<'_, PoolState>, Error> {
+        self.inner.lock_state(pool_key)
+    }
+
+    /// Reserves a channel slot on an existing connection, unless other callers are
+    /// already waiting for one.
+    fn try_reserve_channel(
+        &self,
+        pool_key: &PoolKey,
+        profile: Option<&str>,
+    ) -> Result<Option<Reservation>, Error> {
+        let evicted_connections = self.lock_state(pool_key)?.evict_dead_connections();
+
+        if evicted_connections > 0 {
+            self.inner.start_recovery(pool_key, &Handle::current());
+        }
+
+        let mut state = self.lock_state(pool_key)?;
+
+        if state.closing {
+            return Err(closed_error());
+        }
+
+        if state.has_waiters() {
+            return Ok(None);
+        }
+
+        Ok(state.try_reserve_channel(profile))
+    }
+
+    /// Opens a new connection with a channel slot already reserved on it, unless
+    /// another caller made room in the pool while this one was waiting for its turn,
+    /// backing off as the recovery does after a failed connection.
+    ///
+    /// Returns `None` when the pool already holds as many connections as allowed, or
+    /// when the callers already queued took every slot of the new connection.
+    async fn try_connect(
+        &self,
+        pool_key: &PoolKey,
+        profile: Option<&str>,
+    ) -> Result<Option<Reservation>, Error> {
+        // no need to queue behind the connection attempt probing the broker
+        self.inner.check_circuit()?;
+
+        let _connect_guard = self.inner.pool(pool_key)?.connect_lock.lock().await;
+
+        // the broker is given some rest after the last connection failed
+        let next_connect_at = self.lock_state(pool_key)?.next_connect_at;
+
+        if let Some(next_connect_at) = next_connect_at {
+            tokio::time::sleep_until(next_connect_at.into()).await;
+        }
+
+        if let Some(reservation) = self.try_reserve_channel(pool_key, profile)? {
+            return Ok(Some(reservation));
+        }
+
+        let (unused_connections, limit_reached) = {
+            let mut state = self.lock_state(pool_key)?;
+
+            // connections without channels alive are not worth keeping around
+            let unused_connections = state.take_idle_connections(Duration::ZERO, 0);
+
+            let limit_reached = match state.pool_config.max_connections() {
+                Some(max_connections) => state.active_connection_count() >= max_connections,
+                None => false,
+            };
+
+            if limit_reached {
+                state.counters.limit_hits += 1;
             }
+
+            (unused_connections, limit_reached)
         };
 
-        self.connections.push(connection);
+        for unused_connection in unused_connections {
+            close_connection(&unused_connection, "unused connection").await;
+        }
 
-        let borrowed_connection = match self.connections.last() {
-            Some(connection) => connection,
-            None => {
-                return Err(Error::new(
-                    ErrorKind::InternalFailure,
-                    "failed to get previously created connection",
-                ))
+        if limit_reached {
+            return Ok(None);
+        }
+
+        let connection = self.inner.open_connection(pool_key).await?;
+
+        self.inner
+            .push_connection(pool_key, PooledConnection::new(connection))
+            .await?;
+
+        let mut state = self.lock_state(pool_key)?;
+
+        // the callers queued before this one got served first
+        if state.has_waiters() {
+            return Ok(None);
+        }
+
+        Ok(state.try_reserve_channel(profile))
+    }
+
+    /// Queues the caller until a channel slot is released, for as long as the
+    /// acquire timeout allows it.
+    async fn wait_for_channel(
+        &self,
+        pool_key: &PoolKey,
+        profile: Option<&str>,
+    ) -> Result<Reservation, Error> {
+        let (sender, receiver) = oneshot::channel();
+        let waiter = Waiter {
+            sender,
+            profile: profile.map(str::to_string),
+        };
+        let mut waiter_receiver = WaiterReceiver {
+            receiver,
+            wrapper: self,
+            pool_key,
+        };
+
+        let acquire_timeout = {
+            let mut state = self.lock_state(pool_key)?;
+
+            // a channel may have been released since the pool was last looked at
+            if !state.has_waiters() {
+                if let Some(reservation) = state.try_reserve_channel(profile) {
+                    return Ok(reservation);
+                }
+            }
+
+            match state.pool_config.acquire_timeout() {
+                Some(acquire_timeout) => {
+                    state.waiters.push_back(waiter);
+
+                    acquire_timeout
+                }
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::PoolExhausted,
+                        format!(
+                            "no channel available within the limit of {} connections",
+                            state.pool_config.max_connections().unwrap_or_default()
+                        ),
+                    ))
+                }
             }
         };
 
-        Ok(borrowed_connection)
+        match tokio::time::timeout(acquire_timeout, &mut waiter_receiver.receiver).await {
+            Ok(Ok(reservation)) => Ok(reservation),
+            // waiters are only dropped when the wrapper gets closed
+            Ok(Err(_)) => Err(closed_error()),
+            Err(_) => {
+                // a slot may have been handed over right when the timeout elapsed
+                waiter_receiver.receiver.close();
+
+                match waiter_receiver.receiver.try_recv() {
+                    Ok(reservation) => Ok(reservation),
+                    Err(_) => Err(Error::new(
+                        ErrorKind::AcquireTimeout,
+                        format!(
+                            "no channel was released within {} ms",
+                            acquire_timeout.as_millis()
+                        ),
+                    )),
+                }
+            }
+        }
     }
 
-    async fn get_connection(&mut self) -> Result<&WrappedConnection, Error> {
-        if self.connections.is_empty() {
-            return self.try_connect().await;
+    /// Reserves a channel slot for the caller, opening a new connection or waiting
+    /// for a channel to be released when needed.
+    async fn reserve_channel(
+        &self,
+        pool_key: &PoolKey,
+        profile: Option<&str>,
+    ) -> Result<Reservation, Error> {
+        if let Some(reservation) = self.try_reserve_channel(pool_key, profile)? {
+            return Ok(reservation);
         }
 
-        let last_connection = match self.connections.last() {
-            Some(last_connection) => last_connection,
-            None => {
+        match self.try_connect(pool_key, profile).await? {
+            Some(reservation) => Ok(reservation),
+            None => self.wait_for_channel(pool_key, profile).await,
+        }
+    }
+
+    /// Gives back a channel slot reserved for a caller which no longer needs it.
+    fn cancel_reservation(
+        &self,
+        pool_key: &PoolKey,
+        reservation: Reservation,
+    ) -> Result<(), Error> {
+        let mut state = self.lock_state(pool_key)?;
+
+        state.cancel_reservation(reservation);
+        state.dispatch_waiters();
+
+        Ok(())
+    }
+
+    /// Applies the blocked policy to a publish channel request served by `connection`.
+    async fn wait_until_unblocked(
+        &self,
+        pool_key: &PoolKey,
+        connection: &WrappedConnection,
+    ) -> Result<(), Error> {
+        let blocked_config = self.inner.connect_config.blocked_config();
+
+        if !connection.connection.status().blocked() {
+            return Ok(());
+        }
+
+        let blocked_error = || {
+            Error::new(
+                ErrorKind::ConnectionBlocked,
+                format!("connection to {} is blocked by the broker", connection.node),
+            )
+        };
+
+        match blocked_config.policy() {
+            BlockedPolicy::Ignore => Ok(()),
+            BlockedPolicy::FailFast => Err(blocked_error()),
+            BlockedPolicy::Wait => {
+                let deadline = self
+                    .lock_state(pool_key)?
+                    .pool_config
+                    .acquire_timeout()
+                    .map(|acquire_timeout| Instant::now() + acquire_timeout);
+
+                while connection.connection.status().blocked() {
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Err(blocked_error());
+                    }
+
+                    tokio::time::sleep(blocked_config.poll_interval()).await;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Gives back a channel slot reserved on a connection which failed to open the channel.
+    fn release_reserved_channel(
+        &self,
+        pool_key: &PoolKey,
+        connection_id: &str,
+        full: bool,
+    ) -> Result<(), Error> {
+        let mut state = self.lock_state(pool_key)?;
+
+        state.release_pending_channel(connection_id, full);
+        state.dispatch_waiters();
+
+        Ok(())
+    }
+
+    /// Records a newly created channel as leased, returning whether its connection
+    /// was still in the pool.
+    fn register_channel(
+        &self,
+        pool_key: &PoolKey,
+        connection_id: &str,
+        channel: &Channel,
+    ) -> Result<bool, Error> {
+        let mut state = self.lock_state(pool_key)?;
+
+        match state.get_connection_mut(connection_id) {
+            Some(pooled_connection) => {
+                pooled_connection.pending_channels -= 1;
+                pooled_connection
+                    .leased_channels
+                    .insert(channel.id(), channel.clone());
+                state.counters.channels_created += 1;
+
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Leases a channel from the shared pool.
+    pub async fn try_get_channel(&self) -> Result<ChannelLease, Error> {
+        self.get_channel(&PoolKey::Shared, None, None).await
+    }
+
+    /// Leases a channel set up with the channel profile registered as `profile`,
+    /// from the shared pool.
+    pub async fn try_get_channel_with_profile(&self, profile: &str) -> Result<ChannelLease, Error> {
+        self.get_channel(&PoolKey::Shared, None, Some(profile))
+            .await
+    }
+
+    /// Leases a channel set up with the channel profile registered as `profile`,
+    /// from the pool serving `purpose`.
+    pub async fn try_get_channel_for_with_profile(
+        &self,
+        purpose: ChannelPurpose,
+        profile: &str,
+    ) -> Result<ChannelLease, Error> {
+        self.get_channel(
+            &self.inner.pool_key_for(purpose),
+            Some(purpose),
+            Some(profile),
+        )
+        .await
+    }
+
+    /// Leases a channel from the pool dedicated to `purpose`, or from the shared pool
+    /// when the purpose has no dedicated one.
+    ///
+    /// Publish channels served by a connection blocked by the broker are subject to
+    /// the blocked policy.
+    pub async fn try_get_channel_for(
+        &self,
+        purpose: ChannelPurpose,
+    ) -> Result<ChannelLease, Error> {
+        self.get_channel(&self.inner.pool_key_for(purpose), Some(purpose), None)
+            .await
+    }
+
+    pub async fn try_get_publish_channel(&self) -> Result<ChannelLease, Error> {
+        self.try_get_channel_for(ChannelPurpose::Publish).await
+    }
+
+    pub async fn try_get_consume_channel(&self) -> Result<ChannelLease, Error> {
+        self.try_get_channel_for(ChannelPurpose::Consume).await
+    }
+
+    /// Leases a channel from the connections reserved for the affinity group `group`.
+    pub async fn try_get_channel_in(&self, group: &str) -> Result<ChannelLease, Error> {
+        self.get_channel(&self.group_pool_key(group)?, None, None)
+            .await
+    }
+
+    /// Leases a channel set up with the channel profile registered as `profile`,
+    /// from the connections reserved for the affinity group `group`.
+    pub async fn try_get_channel_in_with_profile(
+        &self,
+        group: &str,
+        profile: &str,
+    ) -> Result<ChannelLease, Error> {
+        self.get_channel(&self.group_pool_key(group)?, None, Some(profile))
+            .await
+    }
+
+    /// Begins an AMQP transaction on a channel of the shared pool put in tx mode.
+    ///
+    /// Such channels are only reused by other transactions, once committed or rolled
+    /// back.
+    pub async fn try_begin_transaction(&self) -> Result<Transaction, Error> {
+        let channel_profile = ChannelProfile::new().with_transactional(true);
+        let channel_lease = self
+            .lease_channel(
+                &PoolKey::Shared,
+                None,
+                Some(TRANSACTION_PROFILE),
+                Some(&channel_profile),
+            )
+            .await?;
+
+        Ok(Transaction::new(channel_lease))
+    }
+
+    fn group_pool_key(&self, group: &str) -> Result<PoolKey, Error> {
+        let pool_key = PoolKey::Group(group.to_string());
+
+        if self.inner.pools.contains_key(&pool_key) {
+            Ok(pool_key)
+        } else {
+            Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("unknown affinity group '{}'", group),
+            ))
+        }
+    }
+
+    /// Leases a channel set up with the channel profile registered as `profile`, if any.
+    async fn get_channel(
+        &self,
+        pool_key: &PoolKey,
+        purpose: Option<ChannelPurpose>,
+        profile: Option<&str>,
+    ) -> Result<ChannelLease, Error> {
+        let channel_profile = match profile {
+            Some(profile) => match self.inner.connect_config.channel_profile(profile) {
+                Some(channel_profile) => Some(channel_profile),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::ConfigFailure,
+                        format!("unknown channel profile '{}'", profile),
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        self.lease_channel(pool_key, purpose, profile, channel_profile)
+            .await
+    }
+
+    /// Leases a channel, trying again as the retry policy allows whenever the broker
+    /// refuses to open one or the picked connection is lost.
+    async fn lease_channel(
+        &self,
+        pool_key: &PoolKey,
+        purpose: Option<ChannelPurpose>,
+        profile: Option<&str>,
+        channel_profile: Option<&ChannelProfile>,
+    ) -> Result<ChannelLease, Error> {
+        let retry_config = self.inner.connect_config.retry_config();
+        let deadline = retry_config
+            .deadline()
+            .map(|deadline| tokio::time::Instant::now() + deadline);
+        let mut backoff = Backoff::new(retry_config.backoff().clone());
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            let attempt = self.try_lease_channel(pool_key, purpose, profile, channel_profile);
+
+            // an attempt still running at the deadline is given up, its slot released
+            let attempt = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, attempt).await {
+                    Ok(attempt) => attempt?,
+                    Err(_) => {
+                        return Err(Error::new(
+                            ErrorKind::RetriesExhausted,
+                            format!(
+                                "gave up leasing a channel at the deadline, during attempt {}",
+                                attempts
+                            ),
+                        ))
+                    }
+                },
+                None => attempt.await?,
+            };
+
+            let reason = match attempt {
+                Attempt::Leased(channel_lease) => return Ok(*channel_lease),
+                Attempt::Retry(reason) => reason,
+            };
+
+            if attempts >= retry_config.max_attempts() {
                 return Err(Error::new(
-                    ErrorKind::InternalFailure,
-                    "failed to get last connection after checking there are available connections",
+                    ErrorKind::RetriesExhausted,
+                    format!(
+                        "gave up leasing a channel after {} attempts: {}",
+                        attempts, reason
+                    ),
+                ));
+            }
+
+            let retry_at = tokio::time::Instant::now() + backoff.next_delay();
+
+            if deadline.is_some_and(|deadline| retry_at >= deadline) {
+                return Err(Error::new(
+                    ErrorKind::RetriesExhausted,
+                    format!(
+                        "gave up leasing a channel at the deadline, after {} attempts: {}",
+                        attempts, reason
+                    ),
                 ));
             }
-        };
 
-        Ok(last_connection)
+            tokio::time::sleep_until(retry_at).await;
+        }
     }
 
-    #[async_recursion]
-    pub async fn try_get_channel(&mut self) -> Result<Arc<Channel>, Error> {
-        let wrapped_connection = self.get_connection().await?;
-        let connection_id = wrapped_connection.id.clone();
+    async fn try_lease_channel(
+        &self,
+        pool_key: &PoolKey,
+        purpose: Option<ChannelPurpose>,
+        profile: Option<&str>,
+        channel_profile: Option<&ChannelProfile>,
+    ) -> Result<Attempt, Error> {
+        let reservation = self.reserve_channel(pool_key, profile).await?;
+        let wrapped_connection = reservation.connection.clone();
+        let mut reserved_slot = ReservedSlot {
+            wrapper: self,
+            pool_key,
+            reservation: Some(reservation),
+            channel: None,
+        };
+
+        if purpose == Some(ChannelPurpose::Publish) {
+            self.wait_until_unblocked(pool_key, &wrapped_connection)
+                .await?;
+        }
+
+        // consumers must not be handed over to the next caller along with the channel
+        let reusable = purpose != Some(ChannelPurpose::Consume);
+
+        let idle_channel = reserved_slot
+            .reservation
+            .as_mut()
+            .and_then(|reservation| reservation.idle_channel.take());
+
+        if let Some(idle_channel) = idle_channel {
+            if idle_channel.channel.status().connected() {
+                reserved_slot.disarm();
+
+                return Ok(Attempt::Leased(Box::new(ChannelLease::new(
+                    idle_channel.channel,
+                    wrapped_connection.id.clone(),
+                    wrapped_connection.node.clone(),
+                    idle_channel.profile,
+                    pool_key.clone(),
+                    &self.inner,
+                    reusable,
+                ))));
+            }
+
+            // the channel got closed while waiting for the connection to be unblocked
+            if !self
+                .lock_state(pool_key)?
+                .discard_reserved_channel(&wrapped_connection.id, idle_channel.channel.id())
+            {
+                reserved_slot.disarm();
+
+                return Ok(Attempt::Retry(
+                    "connection was removed from the pool while leasing a channel".to_string(),
+                ));
+            }
+        }
 
         let channel = match wrapped_connection.connection.create_channel().await {
-            Ok(channel) => Arc::new(channel),
+            Ok(channel) => channel,
             Err(error) => {
-                if error == lapin::Error::ChannelsLimitReached {
-                    self.try_get_channel_from_new_connection().await?
+                reserved_slot.disarm();
+
+                let channels_limit_reached = error == lapin::Error::ChannelsLimitReached;
+                self.release_reserved_channel(
+                    pool_key,
+                    &wrapped_connection.id,
+                    channels_limit_reached,
+                )?;
+
+                // the connection died after being picked, another one can serve the caller
+                let connection_lost = !wrapped_connection.connection.status().connected();
+
+                if connection_lost {
+                    self.inner.evict_connection(
+                        pool_key,
+                        &wrapped_connection.id,
+                        &Handle::current(),
+                    );
+                }
+
+                if channels_limit_reached || connection_lost {
+                    return Ok(Attempt::Retry(format!(
+                        "failed to create channel: {}",
+                        error
+                    )));
                 } else {
                     return Err(Error::new(
                         ErrorKind::InternalFailure,
@@ -117,44 +2394,133 @@ impl AmqpWrapper {
             }
         };
 
-        let channel_weak = Arc::downgrade(&channel);
+        self.inner
+            .watch_channel(pool_key, &wrapped_connection.id, &channel);
+        reserved_slot.channel = Some(channel.clone());
 
-        match self.channels_per_connection.get_mut(&connection_id) {
-            Some(channels) => channels.push(channel_weak),
-            None => {
-                self.channels_per_connection
-                    .insert(connection_id, vec![channel_weak]);
+        if let Some(channel_profile) = channel_profile {
+            if let Err(error) = apply_channel_profile(&channel, channel_profile).await {
+                return Err(Error::new(
+                    ErrorKind::InternalFailure,
+                    format!(
+                        "failed to apply channel profile '{}': {}",
+                        profile.unwrap_or_default(),
+                        error
+                    ),
+                ));
             }
         }
 
-        Ok(channel)
+        reserved_slot.disarm();
+
+        // the connection died while the channel was being created
+        if !self.register_channel(pool_key, &wrapped_connection.id, &channel)? {
+            close_channel(channel);
+
+            return Ok(Attempt::Retry(
+                "connection was removed from the pool while creating a channel".to_string(),
+            ));
+        }
+
+        Ok(Attempt::Leased(Box::new(ChannelLease::new(
+            channel,
+            wrapped_connection.id.clone(),
+            wrapped_connection.node.clone(),
+            profile.map(str::to_string),
+            pool_key.clone(),
+            &self.inner,
+            reusable,
+        ))))
     }
 
-    async fn try_get_channel_from_new_connection(&mut self) -> Result<Arc<Channel>, Error> {
-        self.try_connect().await?;
+    /// Stops handing out channels and closes every channel and connection of the pools
+    /// with reply code 200, waiting first for the publisher confirms outstanding on
+    /// the channels in confirm mode.
+    ///
+    /// Whatever is not closed by the time `timeout` elapses is listed in the report.
+    /// Closing an already closed wrapper has nothing left to close.
+    pub async fn close(&self, timeout: Duration) -> Result<CloseReport, Error> {
+        let deadline = tokio::time::Instant::now() + timeout;
 
-        let result = self.try_get_channel().await;
+        let mut connections = Vec::new();
 
-        // clean connections that have no channels alive
-        self.clean_connections_and_channels().await;
+        for pool in self.inner.pools.values() {
+            let mut state = pool.lock_state()?;
 
-        result
-    }
+            state.closing = true;
+            state.waiters.clear();
+
+            connections.append(&mut state.connections);
+        }
 
-    async fn clean_connections_and_channels(&mut self) {
-        let mut empty_connection_ids: Vec<&str> = Vec::new();
+        let mut report = CloseReport::default();
+        let mut channel_closes = JoinSet::new();
 
-        for pair in self.channels_per_connection.iter_mut() {
-            pair.1
-                .retain(|weak_channel| weak_channel.strong_count() > 0);
+        for pooled_connection in connections.iter() {
+            let channels = pooled_connection.leased_channels.values().chain(
+                pooled_connection
+                    .idle_channels
+                    .iter()
+                    .map(|idle_channel| &idle_channel.channel),
+            );
 
-            if pair.1.is_empty() {
-                empty_connection_ids.push(pair.0)
+            for channel in channels {
+                let channel = channel.clone();
+                let connection_id = pooled_connection.connection.id.clone();
+
+                channel_closes.spawn(async move {
+                    let result = close_channel_gracefully(&channel, deadline).await;
+
+                    (connection_id, channel.id(), result)
+                });
             }
         }
 
-        self.connections.retain(|wrapped_connection| {
-            !empty_connection_ids.contains(&wrapped_connection.id.as_str())
-        });
+        while let Some(channel_close) = channel_closes.join_next().await {
+            if let Ok((connection_id, channel_id, Err(reason))) = channel_close {
+                report.unclosed_channels.push(UnclosedChannel {
+                    connection_id,
+                    channel_id,
+                    reason,
+                });
+            }
+        }
+
+        let mut connection_closes = JoinSet::new();
+
+        for pooled_connection in connections {
+            let connection = pooled_connection.connection;
+
+            connection_closes.spawn(async move {
+                let result = if connection.connection.status().connected() {
+                    tokio::time::timeout_at(
+                        deadline,
+                        connection.connection.close(REPLY_SUCCESS, "OK"),
+                    )
+                    .await
+                } else {
+                    Ok(Ok(()))
+                };
+
+                let result = match result {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(error)) => Err(format!("failed to close connection: {}", error)),
+                    Err(_) => Err("connection did not close in time".to_string()),
+                };
+
+                (connection.id.clone(), result)
+            });
+        }
+
+        while let Some(connection_close) = connection_closes.join_next().await {
+            if let Ok((connection_id, Err(reason))) = connection_close {
+                report.unclosed_connections.push(UnclosedConnection {
+                    connection_id,
+                    reason,
+                });
+            }
+        }
+
+        Ok(report)
     }
 }
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::config::backoff_config::BackoffConfig;
+
+/// Computes the delays to wait between consecutive attempts of an operation.
+pub(crate) struct Backoff {
+    config: BackoffConfig,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub(crate) fn new(config: BackoffConfig) -> Backoff {
+        Backoff { config, attempt: 0 }
+    }
+
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let exponential_delay = self.config.initial_delay().as_secs_f64()
+            * self.config.multiplier().powi(self.attempt as i32);
+        let delay = exponential_delay.min(self.config.max_delay().as_secs_f64());
+
+        self.attempt = self.attempt.saturating_add(1);
+
+        let jitter = delay * self.config.jitter() * rand::thread_rng().gen::<f64>();
+
+        Duration::from_secs_f64(delay - jitter)
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    fmt,
+    ops::Deref,
+    sync::{Arc, Weak},
+};
+
+use lapin::{protocol::constants::REPLY_SUCCESS, Channel};
+
+use crate::amqp_wrapper::{AmqpWrapperInner, PoolKey};
+use crate::error::{Error, ErrorKind};
+
+/// Channel handed out by `AmqpWrapper`.
+///
+/// Dropping the lease gives the channel back to the pool, which keeps it open for
+/// the next caller when it is still healthy and closes it otherwise. Channels leased
+/// for consuming are always closed, so their consumers are never handed over.
+/// Wrap the lease in an `Arc` to share it between tasks.
+pub struct ChannelLease {
+    channel: Channel,
+    connection_id: String,
+    node: String,
+    profile: Option<String>,
+    pool_key: PoolKey,
+    pool: Weak<AmqpWrapperInner>,
+    reusable: bool,
+}
+
+impl ChannelLease {
+    pub(crate) fn new(
+        channel: Channel,
+        connection_id: String,
+        node: String,
+        profile: Option<String>,
+        pool_key: PoolKey,
+        pool: &Arc<AmqpWrapperInner>,
+        reusable: bool,
+    ) -> ChannelLease {
+        ChannelLease {
+            channel,
+            connection_id,
+            node,
+            profile,
+            pool_key,
+            pool: Arc::downgrade(pool),
+            reusable,
+        }
+    }
+
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    pub fn connection_id(&self) -> &str {
+        self.connection_id.as_str()
+    }
+
+    /// Broker node, as `host:port`, the channel's connection is attached to.
+    pub fn node(&self) -> &str {
+        self.node.as_str()
+    }
+
+    /// Name of the channel profile the channel was set up with, if any.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Closes the channel instead of giving it back to the pool for reuse.
+    pub async fn close(self) -> Result<(), Error> {
+        match self.channel.close(REPLY_SUCCESS, "OK").await {
+            Ok(()) => Ok(()),
+            Err(error) => Err(Error::new(
+                ErrorKind::InternalFailure,
+                format!("failed to close channel: {}", error),
+            )),
+        }
+    }
+
+    /// Gives the channel back to the pool without letting it be reused, for when its
+    /// state is unknown and it cannot be closed right away.
+    pub(crate) fn discard(mut self) {
+        self.reusable = false;
+    }
+}
+
+impl Deref for ChannelLease {
+    type Target = Channel;
+
+    fn deref(&self) -> &Channel {
+        &self.channel
+    }
+}
+
+impl fmt::Debug for ChannelLease {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChannelLease")
+            .field("channel_id", &self.channel.id())
+            .field("connection_id", &self.connection_id)
+            .field("node", &self.node)
+            .field("profile", &self.profile)
+            .finish()
+    }
+}
+
+impl Drop for ChannelLease {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.upgrade() {
+            pool.release_channel(
+                &self.pool_key,
+                &self.connection_id,
+                self.channel.clone(),
+                self.profile.clone(),
+                self.reusable,
+            );
+        }
+    }
+}
@@ -0,0 +1,9 @@
+/// What a channel is leased for.
+///
+/// RabbitMQ throttles publishers per connection, so giving each purpose a dedicated
+/// pool keeps throttled publishers from stalling the consumers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChannelPurpose {
+    Publish,
+    Consume,
+}
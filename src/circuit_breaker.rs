@@ -0,0 +1,86 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::config::circuit_breaker_config::CircuitBreakerConfig;
+
+/// State of the circuit breaker guarding the connections to the broker.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Connections are attempted as usual.
+    #[default]
+    Closed,
+    /// The broker is deemed down, no connection is attempted.
+    Open,
+    /// A single connection attempt is probing whether the broker is back.
+    HalfOpen,
+}
+
+/// Tracks the consecutive failures to connect, and which attempts may go on.
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: CircuitState,
+    consecutive_failures: u32,
+    // When the circuit opened, or when the half-open probe started.
+    changed_at: Instant,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> CircuitBreaker {
+        CircuitBreaker {
+            config,
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            changed_at: Instant::now(),
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Whether an attempt started at `now` would be refused.
+    ///
+    /// A probe which never reported back, e.g. because its caller gave up on it,
+    /// is replaced by another one after the reset timeout.
+    pub(crate) fn is_open(&self, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed => false,
+            CircuitState::Open | CircuitState::HalfOpen => {
+                now.duration_since(self.changed_at) < self.config.reset_timeout()
+            }
+        }
+    }
+
+    /// Lets a connection attempt through unless the circuit is open, turning the
+    /// first attempt after the reset timeout into the half-open probe.
+    pub(crate) fn try_acquire(&mut self, now: Instant) -> bool {
+        if self.is_open(now) {
+            return false;
+        }
+
+        if self.state != CircuitState::Closed {
+            self.state = CircuitState::HalfOpen;
+            self.changed_at = now;
+        }
+
+        true
+    }
+
+    pub(crate) fn on_success(&mut self) {
+        self.state = CircuitState::Closed;
+        self.consecutive_failures = 0;
+    }
+
+    pub(crate) fn on_failure(&mut self, now: Instant) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+
+        if self.state == CircuitState::HalfOpen
+            || self.consecutive_failures >= self.config.failure_threshold()
+        {
+            self.state = CircuitState::Open;
+            self.changed_at = now;
+        }
+    }
+}
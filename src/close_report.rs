@@ -0,0 +1,30 @@
+use lapin::types::ChannelId;
+
+/// Channel which `AmqpWrapper::close` could not close gracefully.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnclosedChannel {
+    pub connection_id: String,
+    pub channel_id: ChannelId,
+    pub reason: String,
+}
+
+/// Connection which `AmqpWrapper::close` could not close gracefully.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnclosedConnection {
+    pub connection_id: String,
+    pub reason: String,
+}
+
+/// Outcome of `AmqpWrapper::close`, listing what did not close in time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CloseReport {
+    pub unclosed_channels: Vec<UnclosedChannel>,
+    pub unclosed_connections: Vec<UnclosedConnection>,
+}
+
+impl CloseReport {
+    /// Whether every channel and connection was closed gracefully.
+    pub fn is_complete(&self) -> bool {
+        self.unclosed_channels.is_empty() && self.unclosed_connections.is_empty()
+    }
+}
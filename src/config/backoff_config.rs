@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::error::{Error, ErrorKind};
+
+const INITIAL_DELAY_KEY: &str = "initial_delay_ms";
+const MAX_DELAY_KEY: &str = "max_delay_ms";
+const MULTIPLIER_KEY: &str = "multiplier";
+const JITTER_KEY: &str = "jitter";
+
+const DEFAULT_INITIAL_DELAY: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(30);
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_JITTER: f64 = 0.5;
+
+/// Exponential backoff between consecutive attempts of an operation.
+///
+/// The delay starts at `initial_delay`, is multiplied by `multiplier` after every
+/// attempt and never exceeds `max_delay`. Up to a `jitter` fraction of each delay
+/// is randomly taken off, so that many clients do not retry in lockstep.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackoffConfig {
+    initial_delay: Duration,
+    max_delay: Duration,
+    multiplier: f64,
+    jitter: f64,
+}
+
+impl BackoffConfig {
+    pub fn new(initial_delay: Duration, max_delay: Duration) -> BackoffConfig {
+        BackoffConfig {
+            initial_delay,
+            max_delay,
+            ..BackoffConfig::default()
+        }
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn initial_delay(&self) -> Duration {
+        self.initial_delay
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    pub fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.initial_delay > self.max_delay {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!(
+                    "'{}' must not exceed '{}'",
+                    INITIAL_DELAY_KEY, MAX_DELAY_KEY
+                ),
+            ));
+        }
+
+        if self.multiplier < 1.0 {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be at least 1", MULTIPLIER_KEY),
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be between 0 and 1", JITTER_KEY),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        BackoffConfig {
+            initial_delay: DEFAULT_INITIAL_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            multiplier: DEFAULT_MULTIPLIER,
+            jitter: DEFAULT_JITTER,
+        }
+    }
+}
+
+/// Parses a backoff, the keys left out keeping their value in `backoff_config`.
+pub(crate) fn get_backoff_config_from_json_object(
+    object: &mut Map<String, Value>,
+    mut backoff_config: BackoffConfig,
+) -> Result<BackoffConfig, Error> {
+    if let Some(initial_delay) = get_optional_value_from_json_object(object, INITIAL_DELAY_KEY)? {
+        backoff_config.initial_delay = Duration::from_millis(initial_delay);
+    }
+
+    if let Some(max_delay) = get_optional_value_from_json_object(object, MAX_DELAY_KEY)? {
+        backoff_config.max_delay = Duration::from_millis(max_delay);
+    }
+
+    if let Some(multiplier) = get_optional_value_from_json_object(object, MULTIPLIER_KEY)? {
+        backoff_config.multiplier = multiplier;
+    }
+
+    if let Some(jitter) = get_optional_value_from_json_object(object, JITTER_KEY)? {
+        backoff_config.jitter = jitter;
+    }
+
+    Ok(backoff_config)
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::error::{Error, ErrorKind};
+
+const POLICY_KEY: &str = "policy";
+const POLL_INTERVAL_KEY: &str = "poll_interval_ms";
+
+const IGNORE: &str = "ignore";
+const FAIL_FAST: &str = "fail_fast";
+const WAIT: &str = "wait";
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What happens to a publish channel request served by a connection the broker
+/// blocked, after a memory or disk alarm.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BlockedPolicy {
+    /// The channel is handed out anyway, publishing on it hangs until unblocked.
+    #[default]
+    Ignore,
+    /// The request fails right away.
+    FailFast,
+    /// The request waits for the connection to be unblocked, for as long as the
+    /// acquire timeout of the pool allows it.
+    Wait,
+}
+
+impl BlockedPolicy {
+    pub fn try_from_name(name: &str) -> Result<BlockedPolicy, Error> {
+        match name {
+            IGNORE => Ok(BlockedPolicy::Ignore),
+            FAIL_FAST => Ok(BlockedPolicy::FailFast),
+            WAIT => Ok(BlockedPolicy::Wait),
+            _ => Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!(
+                    "unknown blocked policy '{}', expected '{}', '{}' or '{}'",
+                    name, IGNORE, FAIL_FAST, WAIT
+                ),
+            )),
+        }
+    }
+}
+
+/// Tracking of the connections blocked by the broker, whose state is polled every
+/// `poll_interval` since lapin does not notify it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockedConfig {
+    policy: BlockedPolicy,
+    poll_interval: Duration,
+}
+
+impl BlockedConfig {
+    pub fn new(policy: BlockedPolicy) -> BlockedConfig {
+        BlockedConfig {
+            policy,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn policy(&self) -> BlockedPolicy {
+        self.policy
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.poll_interval.is_zero() {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be greater than 0", POLL_INTERVAL_KEY),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for BlockedConfig {
+    fn default() -> Self {
+        BlockedConfig::new(BlockedPolicy::default())
+    }
+}
+
+pub(crate) fn get_blocked_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<BlockedConfig, Error> {
+    let mut blocked_config = BlockedConfig::default();
+
+    if let Some(policy) = get_optional_value_from_json_object::<String>(object, POLICY_KEY)? {
+        blocked_config.policy = BlockedPolicy::try_from_name(&policy)?;
+    }
+
+    if let Some(poll_interval) = get_optional_value_from_json_object(object, POLL_INTERVAL_KEY)? {
+        blocked_config.poll_interval = Duration::from_millis(poll_interval);
+    }
+
+    Ok(blocked_config)
+}
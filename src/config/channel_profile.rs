@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::error::{Error, ErrorKind};
+
+const PREFETCH_COUNT_KEY: &str = "prefetch_count";
+const GLOBAL_QOS_KEY: &str = "global_qos";
+const CONFIRM_KEY: &str = "confirm";
+const TRANSACTIONAL_KEY: &str = "transactional";
+
+/// Settings applied by `AmqpWrapper` to the channels it opens for a named profile.
+///
+/// Released channels are only handed out again to callers asking for the same
+/// profile, so confirm, transactional and plain channels never get mixed up.
+/// A channel cannot be both in confirm and in transactional mode.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChannelProfile {
+    prefetch_count: Option<u16>,
+    global_qos: bool,
+    confirm: bool,
+    transactional: bool,
+}
+
+impl ChannelProfile {
+    pub fn new() -> ChannelProfile {
+        ChannelProfile::default()
+    }
+
+    pub fn with_prefetch_count(mut self, prefetch_count: u16) -> Self {
+        self.prefetch_count = Some(prefetch_count);
+        self
+    }
+
+    /// Applies the prefetch count to the whole channel instead of each consumer.
+    pub fn with_global_qos(mut self, global_qos: bool) -> Self {
+        self.global_qos = global_qos;
+        self
+    }
+
+    pub fn with_confirm(mut self, confirm: bool) -> Self {
+        self.confirm = confirm;
+        self
+    }
+
+    pub fn with_transactional(mut self, transactional: bool) -> Self {
+        self.transactional = transactional;
+        self
+    }
+
+    pub fn prefetch_count(&self) -> Option<u16> {
+        self.prefetch_count
+    }
+
+    pub fn global_qos(&self) -> bool {
+        self.global_qos
+    }
+
+    pub fn confirm(&self) -> bool {
+        self.confirm
+    }
+
+    pub fn transactional(&self) -> bool {
+        self.transactional
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.confirm && self.transactional {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!(
+                    "'{}' and '{}' cannot be both enabled",
+                    CONFIRM_KEY, TRANSACTIONAL_KEY
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn get_channel_profiles_from_json_object(
+    object: Map<String, Value>,
+) -> Result<HashMap<String, ChannelProfile>, Error> {
+    let mut channel_profiles = HashMap::new();
+
+    for (name, value) in object {
+        let mut json_channel_profile = match value {
+            Value::Object(json_channel_profile) => json_channel_profile,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::ConfigFailure,
+                    format!("channel profile '{}' must be an object", name),
+                ))
+            }
+        };
+
+        let channel_profile = get_channel_profile_from_json_object(&mut json_channel_profile)?;
+        channel_profiles.insert(name, channel_profile);
+    }
+
+    Ok(channel_profiles)
+}
+
+fn get_channel_profile_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<ChannelProfile, Error> {
+    let channel_profile = ChannelProfile {
+        prefetch_count: get_optional_value_from_json_object(object, PREFETCH_COUNT_KEY)?,
+        global_qos: get_optional_value_from_json_object(object, GLOBAL_QOS_KEY)?
+            .unwrap_or_default(),
+        confirm: get_optional_value_from_json_object(object, CONFIRM_KEY)?.unwrap_or_default(),
+        transactional: get_optional_value_from_json_object(object, TRANSACTIONAL_KEY)?
+            .unwrap_or_default(),
+    };
+
+    Ok(channel_profile)
+}
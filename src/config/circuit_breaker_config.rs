@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::error::{Error, ErrorKind};
+
+const FAILURE_THRESHOLD_KEY: &str = "failure_threshold";
+const RESET_TIMEOUT_KEY: &str = "reset_timeout_ms";
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+const DEFAULT_RESET_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Circuit breaker guarding the connections to the broker.
+///
+/// After `failure_threshold` consecutive failures to connect, the broker is deemed
+/// down and the circuit opens: channels that would need a new connection are
+/// refused right away. Once `reset_timeout` elapsed, a single connection attempt is
+/// let through, closing the circuit again if it succeeds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitBreakerConfig {
+    failure_threshold: u32,
+    reset_timeout: Duration,
+}
+
+impl CircuitBreakerConfig {
+    pub fn new(failure_threshold: u32, reset_timeout: Duration) -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_threshold,
+            reset_timeout,
+        }
+    }
+
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    pub fn reset_timeout(&self) -> Duration {
+        self.reset_timeout
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.failure_threshold == 0 {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be greater than 0", FAILURE_THRESHOLD_KEY),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig::new(DEFAULT_FAILURE_THRESHOLD, DEFAULT_RESET_TIMEOUT)
+    }
+}
+
+pub(crate) fn get_circuit_breaker_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<CircuitBreakerConfig, Error> {
+    let mut circuit_breaker_config = CircuitBreakerConfig::default();
+
+    if let Some(failure_threshold) =
+        get_optional_value_from_json_object(object, FAILURE_THRESHOLD_KEY)?
+    {
+        circuit_breaker_config.failure_threshold = failure_threshold;
+    }
+
+    if let Some(reset_timeout) = get_optional_value_from_json_object(object, RESET_TIMEOUT_KEY)? {
+        circuit_breaker_config.reset_timeout = Duration::from_millis(reset_timeout);
+    }
+
+    Ok(circuit_breaker_config)
+}
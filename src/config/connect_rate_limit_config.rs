@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::error::{Error, ErrorKind};
+
+const MAX_CONNECTS_KEY: &str = "max_connects";
+const INTERVAL_KEY: &str = "interval_ms";
+
+const DEFAULT_MAX_CONNECTS: u32 = 10;
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// At most `max_connects` connection attempts per `interval`, shared by every pool
+/// of the wrapper; the attempts beyond wait for their turn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectRateLimitConfig {
+    max_connects: u32,
+    interval: Duration,
+}
+
+impl ConnectRateLimitConfig {
+    pub fn new(max_connects: u32, interval: Duration) -> ConnectRateLimitConfig {
+        ConnectRateLimitConfig {
+            max_connects,
+            interval,
+        }
+    }
+
+    pub fn max_connects(&self) -> u32 {
+        self.max_connects
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_connects == 0 {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be greater than 0", MAX_CONNECTS_KEY),
+            ));
+        }
+
+        if self.interval.is_zero() {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be greater than 0", INTERVAL_KEY),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ConnectRateLimitConfig {
+    fn default() -> Self {
+        ConnectRateLimitConfig::new(DEFAULT_MAX_CONNECTS, DEFAULT_INTERVAL)
+    }
+}
+
+pub(crate) fn get_connect_rate_limit_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<ConnectRateLimitConfig, Error> {
+    let mut connect_rate_limit_config = ConnectRateLimitConfig::default();
+
+    if let Some(max_connects) = get_optional_value_from_json_object(object, MAX_CONNECTS_KEY)? {
+        connect_rate_limit_config.max_connects = max_connects;
+    }
+
+    if let Some(interval) = get_optional_value_from_json_object(object, INTERVAL_KEY)? {
+        connect_rate_limit_config.interval = Duration::from_millis(interval);
+    }
+
+    Ok(connect_rate_limit_config)
+}
@@ -0,0 +1,59 @@
+use rand::seq::SliceRandom;
+
+use crate::error::{Error, ErrorKind};
+
+const IN_ORDER: &str = "in_order";
+const SHUFFLED: &str = "shuffled";
+const STICKY: &str = "sticky";
+
+/// Order in which the broker URIs are tried when opening a connection, moving on
+/// to the next one whenever connecting fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EndpointOrder {
+    /// From the first URI to the last one, every time.
+    #[default]
+    InOrder,
+    /// In a different random order every time.
+    Shuffled,
+    /// Starting from the last URI a connection was opened to.
+    Sticky,
+}
+
+impl EndpointOrder {
+    pub fn try_from_name(name: &str) -> Result<EndpointOrder, Error> {
+        match name {
+            IN_ORDER => Ok(EndpointOrder::InOrder),
+            SHUFFLED => Ok(EndpointOrder::Shuffled),
+            STICKY => Ok(EndpointOrder::Sticky),
+            _ => Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!(
+                    "unknown endpoint order '{}', expected '{}', '{}' or '{}'",
+                    name, IN_ORDER, SHUFFLED, STICKY
+                ),
+            )),
+        }
+    }
+
+    /// Indexes of `endpoint_count` endpoints in the order they must be tried,
+    /// `last_connected` being the index of the last one connected to.
+    pub(crate) fn endpoint_indexes(
+        &self,
+        endpoint_count: usize,
+        last_connected: usize,
+    ) -> Vec<usize> {
+        let mut indexes: Vec<usize> = (0..endpoint_count).collect();
+
+        match self {
+            EndpointOrder::InOrder => (),
+            EndpointOrder::Shuffled => indexes.shuffle(&mut rand::thread_rng()),
+            EndpointOrder::Sticky => {
+                if endpoint_count > 0 {
+                    indexes.rotate_left(last_connected % endpoint_count);
+                }
+            }
+        }
+
+        indexes
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::error::{Error, ErrorKind};
+
+const INTERVAL_KEY: &str = "interval_secs";
+const MAX_MISSED_KEY: &str = "max_missed";
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+// As many as lapin lets go by before giving a connection up by itself.
+const DEFAULT_MAX_MISSED: u32 = 2;
+
+/// Heartbeat interval requested to the broker for every connection, overriding the
+/// one of the URIs.
+///
+/// A connection from which nothing was received for `max_missed` intervals, e.g.
+/// after a NAT timeout, is deemed dead: it is evicted from its pool and reported
+/// with a `WrapperEvent::ConnectionDead`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeartbeatConfig {
+    interval: Duration,
+    max_missed: u32,
+}
+
+impl HeartbeatConfig {
+    pub fn new(interval: Duration) -> HeartbeatConfig {
+        HeartbeatConfig {
+            interval,
+            max_missed: DEFAULT_MAX_MISSED,
+        }
+    }
+
+    pub fn with_max_missed(mut self, max_missed: u32) -> Self {
+        self.max_missed = max_missed;
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn max_missed(&self) -> u32 {
+        self.max_missed
+    }
+
+    /// Interval in seconds, as negotiated with the broker.
+    pub(crate) fn interval_secs(&self) -> u16 {
+        self.interval.as_secs().min(u16::MAX as u64) as u16
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.interval.as_secs() == 0
+            || self.interval.subsec_nanos() != 0
+            || self.interval.as_secs() > u16::MAX as u64
+        {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!(
+                    "'{}' must be a whole number of seconds between 1 and {}",
+                    INTERVAL_KEY,
+                    u16::MAX
+                ),
+            ));
+        }
+
+        if self.max_missed == 0 {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be at least 1", MAX_MISSED_KEY),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig::new(DEFAULT_INTERVAL)
+    }
+}
+
+pub(crate) fn get_heartbeat_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<HeartbeatConfig, Error> {
+    let mut heartbeat_config = HeartbeatConfig::default();
+
+    if let Some(interval) = get_optional_value_from_json_object(object, INTERVAL_KEY)? {
+        heartbeat_config.interval = Duration::from_secs(interval);
+    }
+
+    if let Some(max_missed) = get_optional_value_from_json_object(object, MAX_MISSED_KEY)? {
+        heartbeat_config.max_missed = max_missed;
+    }
+
+    Ok(heartbeat_config)
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::error::{Error, ErrorKind};
+
+const INTERVAL_KEY: &str = "interval_ms";
+const IDLE_TIMEOUT_KEY: &str = "idle_timeout_ms";
+const MIN_CONNECTIONS_KEY: &str = "min_connections";
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Background task of `AmqpWrapper` tidying up its connections every `interval`.
+///
+/// Connections without leased channels for longer than `idle_timeout` are closed,
+/// as long as at least `min_connections` are kept open, which are opened in advance
+/// when missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JanitorConfig {
+    interval: Duration,
+    idle_timeout: Duration,
+    min_connections: usize,
+}
+
+impl JanitorConfig {
+    pub fn new(interval: Duration, idle_timeout: Duration) -> JanitorConfig {
+        JanitorConfig {
+            interval,
+            idle_timeout,
+            min_connections: 0,
+        }
+    }
+
+    pub fn with_min_connections(mut self, min_connections: usize) -> Self {
+        self.min_connections = min_connections;
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    pub fn min_connections(&self) -> usize {
+        self.min_connections
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.interval.is_zero() {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be greater than 0", INTERVAL_KEY),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for JanitorConfig {
+    fn default() -> Self {
+        JanitorConfig::new(DEFAULT_INTERVAL, DEFAULT_IDLE_TIMEOUT)
+    }
+}
+
+pub(crate) fn get_janitor_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<JanitorConfig, Error> {
+    let mut janitor_config = JanitorConfig::default();
+
+    if let Some(interval) = get_optional_value_from_json_object(object, INTERVAL_KEY)? {
+        janitor_config.interval = Duration::from_millis(interval);
+    }
+
+    if let Some(idle_timeout) = get_optional_value_from_json_object(object, IDLE_TIMEOUT_KEY)? {
+        janitor_config.idle_timeout = Duration::from_millis(idle_timeout);
+    }
+
+    if let Some(min_connections) = get_optional_value_from_json_object(object, MIN_CONNECTIONS_KEY)?
+    {
+        janitor_config.min_connections = min_connections;
+    }
+
+    Ok(janitor_config)
+}
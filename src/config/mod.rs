@@ -1 +1,35 @@
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::error::{Error, ErrorKind};
+
 pub mod amqp_connect_config;
+pub mod backoff_config;
+pub mod blocked_config;
+pub mod channel_profile;
+pub mod circuit_breaker_config;
+pub mod connect_rate_limit_config;
+pub mod endpoint_order;
+pub mod heartbeat_config;
+pub mod janitor_config;
+pub mod pool_config;
+pub mod recovery_config;
+pub mod registry_config;
+pub mod retry_config;
+pub mod warm_up_config;
+
+pub(crate) fn get_optional_value_from_json_object<T: DeserializeOwned>(
+    object: &mut Map<String, Value>,
+    key: &str,
+) -> Result<Option<T>, Error> {
+    match object.remove(key) {
+        Some(value) => match serde_json::from_value::<T>(value) {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("failed to deserialize '{}': {}", key, error),
+            )),
+        },
+        None => Ok(None),
+    }
+}
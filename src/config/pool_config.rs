@@ -0,0 +1,185 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::connection_selector::ConnectionSelection;
+use crate::error::{Error, ErrorKind};
+
+const MAX_CHANNELS_PER_CONNECTION_KEY: &str = "max_channels_per_connection";
+const MAX_CONNECTIONS_KEY: &str = "max_connections";
+const ACQUIRE_TIMEOUT_KEY: &str = "acquire_timeout_ms";
+const MAX_IDLE_CHANNELS_PER_CONNECTION_KEY: &str = "max_idle_channels_per_connection";
+const CONNECTION_SELECTOR_KEY: &str = "connection_selector";
+const MAX_CONNECTION_LIFETIME_KEY: &str = "max_connection_lifetime_ms";
+
+/// Limits applied by `AmqpWrapper` to the connections and channels it opens.
+///
+/// Every limit is optional, `None` meaning that only the broker constrains it.
+/// Without an acquire timeout, asking for a channel once the limits are reached
+/// fails right away instead of waiting for another channel to be released.
+/// Released channels are kept open for reuse up to `max_idle_channels_per_connection`,
+/// the rest being closed.
+///
+/// Connections older than `max_connection_lifetime` are rotated: no new channel is
+/// placed on them, a replacement is opened when a channel is needed, and they are
+/// closed once their last channel is released. Behind a load balancer, this spreads
+/// the connections over the nodes added to the cluster.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PoolConfig {
+    max_channels_per_connection: Option<u16>,
+    max_connections: Option<usize>,
+    acquire_timeout: Option<Duration>,
+    max_idle_channels_per_connection: Option<u16>,
+    connection_selection: ConnectionSelection,
+    max_connection_lifetime: Option<Duration>,
+}
+
+impl PoolConfig {
+    pub fn new() -> PoolConfig {
+        PoolConfig::default()
+    }
+
+    pub fn with_max_channels_per_connection(mut self, max_channels_per_connection: u16) -> Self {
+        self.max_channels_per_connection = Some(max_channels_per_connection);
+        self
+    }
+
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = Some(max_connections);
+        self
+    }
+
+    pub fn with_acquire_timeout(mut self, acquire_timeout: Duration) -> Self {
+        self.acquire_timeout = Some(acquire_timeout);
+        self
+    }
+
+    pub fn with_max_idle_channels_per_connection(
+        mut self,
+        max_idle_channels_per_connection: u16,
+    ) -> Self {
+        self.max_idle_channels_per_connection = Some(max_idle_channels_per_connection);
+        self
+    }
+
+    pub fn with_connection_selection(mut self, connection_selection: ConnectionSelection) -> Self {
+        self.connection_selection = connection_selection;
+        self
+    }
+
+    pub fn with_max_connection_lifetime(mut self, max_connection_lifetime: Duration) -> Self {
+        self.max_connection_lifetime = Some(max_connection_lifetime);
+        self
+    }
+
+    pub fn max_channels_per_connection(&self) -> Option<u16> {
+        self.max_channels_per_connection
+    }
+
+    pub fn max_connections(&self) -> Option<usize> {
+        self.max_connections
+    }
+
+    pub fn acquire_timeout(&self) -> Option<Duration> {
+        self.acquire_timeout
+    }
+
+    pub fn max_idle_channels_per_connection(&self) -> Option<u16> {
+        self.max_idle_channels_per_connection
+    }
+
+    pub fn connection_selection(&self) -> &ConnectionSelection {
+        &self.connection_selection
+    }
+
+    pub fn max_connection_lifetime(&self) -> Option<Duration> {
+        self.max_connection_lifetime
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_channels_per_connection == Some(0) {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!(
+                    "'{}' must be greater than 0",
+                    MAX_CHANNELS_PER_CONNECTION_KEY
+                ),
+            ));
+        }
+
+        if self.max_connections == Some(0) {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be greater than 0", MAX_CONNECTIONS_KEY),
+            ));
+        }
+
+        if self.max_connection_lifetime == Some(Duration::ZERO) {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be greater than 0", MAX_CONNECTION_LIFETIME_KEY),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn get_pool_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<PoolConfig, Error> {
+    let pool_config = PoolConfig {
+        max_channels_per_connection: get_optional_value_from_json_object(
+            object,
+            MAX_CHANNELS_PER_CONNECTION_KEY,
+        )?,
+        max_connections: get_optional_value_from_json_object(object, MAX_CONNECTIONS_KEY)?,
+        acquire_timeout: get_optional_value_from_json_object(object, ACQUIRE_TIMEOUT_KEY)?
+            .map(Duration::from_millis),
+        max_idle_channels_per_connection: get_optional_value_from_json_object(
+            object,
+            MAX_IDLE_CHANNELS_PER_CONNECTION_KEY,
+        )?,
+        connection_selection: match get_optional_value_from_json_object::<String>(
+            object,
+            CONNECTION_SELECTOR_KEY,
+        )? {
+            Some(name) => ConnectionSelection::try_from_name(&name)?,
+            None => ConnectionSelection::default(),
+        },
+        max_connection_lifetime: get_optional_value_from_json_object(
+            object,
+            MAX_CONNECTION_LIFETIME_KEY,
+        )?
+        .map(Duration::from_millis),
+    };
+
+    Ok(pool_config)
+}
+
+/// Parses the pools of the affinity groups, keyed by the name of their group.
+pub(crate) fn get_group_pool_configs_from_json_object(
+    object: Map<String, Value>,
+) -> Result<HashMap<String, PoolConfig>, Error> {
+    let mut pool_configs = HashMap::new();
+
+    for (group, value) in object {
+        let mut json_pool_config = match value {
+            Value::Object(json_pool_config) => json_pool_config,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::ConfigFailure,
+                    format!("affinity group '{}' must be an object", group),
+                ))
+            }
+        };
+
+        pool_configs.insert(
+            group,
+            get_pool_config_from_json_object(&mut json_pool_config)?,
+        );
+    }
+
+    Ok(pool_configs)
+}
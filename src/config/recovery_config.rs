@@ -0,0 +1,72 @@
+use serde_json::{Map, Value};
+
+use crate::config::backoff_config::{get_backoff_config_from_json_object, BackoffConfig};
+use crate::config::get_optional_value_from_json_object;
+use crate::error::Error;
+
+const ENABLED_KEY: &str = "enabled";
+const BACKOFF_KEY: &str = "backoff";
+
+// Every client reconnects at once after a broker restart, unless spread over the
+// whole delay.
+const DEFAULT_JITTER: f64 = 1.0;
+
+/// How `AmqpWrapper` replaces the connections it lost, e.g. after a broker restart.
+///
+/// The backoff also delays the connections opened on demand after a failed one, and
+/// defaults to full jitter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveryConfig {
+    enabled: bool,
+    backoff: BackoffConfig,
+}
+
+impl RecoveryConfig {
+    pub fn new(backoff: BackoffConfig) -> RecoveryConfig {
+        RecoveryConfig {
+            enabled: true,
+            backoff,
+        }
+    }
+
+    pub fn disabled() -> RecoveryConfig {
+        RecoveryConfig {
+            enabled: false,
+            backoff: default_backoff(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn backoff(&self) -> &BackoffConfig {
+        &self.backoff
+    }
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        RecoveryConfig::new(default_backoff())
+    }
+}
+
+fn default_backoff() -> BackoffConfig {
+    BackoffConfig::default().with_jitter(DEFAULT_JITTER)
+}
+
+pub(crate) fn get_recovery_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<RecoveryConfig, Error> {
+    let enabled = get_optional_value_from_json_object(object, ENABLED_KEY)?.unwrap_or(true);
+
+    let backoff =
+        match get_optional_value_from_json_object::<Map<String, Value>>(object, BACKOFF_KEY)? {
+            Some(mut json_backoff) => {
+                get_backoff_config_from_json_object(&mut json_backoff, default_backoff())?
+            }
+            None => default_backoff(),
+        };
+
+    Ok(RecoveryConfig { enabled, backoff })
+}
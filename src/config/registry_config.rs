@@ -0,0 +1,69 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::config::pool_config::PoolConfig;
+use crate::error::{Error, ErrorKind};
+
+/// Settings of a `WrapperRegistry`, on top of the base config shared by every tenant.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegistryConfig {
+    idle_timeout: Option<Duration>,
+    tenant_vhosts: HashMap<String, String>,
+    tenant_pool_configs: HashMap<String, PoolConfig>,
+}
+
+impl RegistryConfig {
+    pub fn new() -> RegistryConfig {
+        RegistryConfig::default()
+    }
+
+    /// Closes and forgets the wrapper of a tenant once it has not been requested
+    /// for `idle_timeout` and none of its channels is leased.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Serves `tenant` from `vhost`, tenants being otherwise served from the vhost
+    /// named after them.
+    pub fn with_tenant_vhost(mut self, tenant: String, vhost: String) -> Self {
+        self.tenant_vhosts.insert(tenant, vhost);
+        self
+    }
+
+    /// Replaces the pool limits of the base config for `tenant`, in the shared pool as
+    /// well as in the dedicated and affinity group pools it configures.
+    pub fn with_tenant_pool_config(mut self, tenant: String, pool_config: PoolConfig) -> Self {
+        self.tenant_pool_configs.insert(tenant, pool_config);
+        self
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    pub fn tenant_vhost<'a>(&'a self, tenant: &'a str) -> &'a str {
+        self.tenant_vhosts
+            .get(tenant)
+            .map(String::as_str)
+            .unwrap_or(tenant)
+    }
+
+    pub fn tenant_pool_config(&self, tenant: &str) -> Option<&PoolConfig> {
+        self.tenant_pool_configs.get(tenant)
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.idle_timeout == Some(Duration::ZERO) {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                "the idle timeout of the tenants must be greater than zero",
+            ));
+        }
+
+        for pool_config in self.tenant_pool_configs.values() {
+            pool_config.validate()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::config::backoff_config::{get_backoff_config_from_json_object, BackoffConfig};
+use crate::config::get_optional_value_from_json_object;
+use crate::error::{Error, ErrorKind};
+
+const MAX_ATTEMPTS_KEY: &str = "max_attempts";
+const DEADLINE_KEY: &str = "deadline_ms";
+const BACKOFF_KEY: &str = "backoff";
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_INITIAL_DELAY: Duration = Duration::from_millis(10);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// How many times `AmqpWrapper` tries to lease a channel when the broker refuses
+/// to open one or the picked connection is lost, before giving up.
+///
+/// The `deadline`, if any, bounds the whole acquisition: an attempt still running
+/// when it passes, e.g. waiting for a channel to be released, is given up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryConfig {
+    max_attempts: u32,
+    backoff: BackoffConfig,
+    deadline: Option<Duration>,
+}
+
+impl RetryConfig {
+    pub fn new(max_attempts: u32) -> RetryConfig {
+        RetryConfig {
+            max_attempts,
+            ..RetryConfig::default()
+        }
+    }
+
+    pub fn with_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub fn backoff(&self) -> &BackoffConfig {
+        &self.backoff
+    }
+
+    pub fn deadline(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_attempts == 0 {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be at least 1", MAX_ATTEMPTS_KEY),
+            ));
+        }
+
+        if self.deadline == Some(Duration::ZERO) {
+            return Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!("'{}' must be greater than zero", DEADLINE_KEY),
+            ));
+        }
+
+        self.backoff.validate()
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff: BackoffConfig::new(DEFAULT_INITIAL_DELAY, DEFAULT_MAX_DELAY),
+            deadline: None,
+        }
+    }
+}
+
+pub(crate) fn get_retry_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<RetryConfig, Error> {
+    let mut retry_config = RetryConfig::default();
+
+    if let Some(max_attempts) = get_optional_value_from_json_object(object, MAX_ATTEMPTS_KEY)? {
+        retry_config.max_attempts = max_attempts;
+    }
+
+    if let Some(deadline) = get_optional_value_from_json_object(object, DEADLINE_KEY)? {
+        retry_config.deadline = Some(Duration::from_millis(deadline));
+    }
+
+    if let Some(mut json_backoff) =
+        get_optional_value_from_json_object::<Map<String, Value>>(object, BACKOFF_KEY)?
+    {
+        retry_config.backoff =
+            get_backoff_config_from_json_object(&mut json_backoff, BackoffConfig::default())?;
+    }
+
+    Ok(retry_config)
+}
@@ -0,0 +1,63 @@
+use serde_json::{Map, Value};
+
+use crate::config::get_optional_value_from_json_object;
+use crate::error::Error;
+
+const CONNECTIONS_KEY: &str = "connections";
+const CHANNELS_PER_CONNECTION_KEY: &str = "channels_per_connection";
+
+const DEFAULT_CONNECTIONS: usize = 1;
+
+/// Connections, and idle channels on each of them, opened by
+/// `AmqpWrapper::try_new_warmed_up` before handing out the wrapper.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WarmUpConfig {
+    connections: usize,
+    channels_per_connection: u16,
+}
+
+impl WarmUpConfig {
+    pub fn new(connections: usize) -> WarmUpConfig {
+        WarmUpConfig {
+            connections,
+            channels_per_connection: 0,
+        }
+    }
+
+    pub fn with_channels_per_connection(mut self, channels_per_connection: u16) -> Self {
+        self.channels_per_connection = channels_per_connection;
+        self
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections
+    }
+
+    pub fn channels_per_connection(&self) -> u16 {
+        self.channels_per_connection
+    }
+}
+
+impl Default for WarmUpConfig {
+    fn default() -> Self {
+        WarmUpConfig::new(DEFAULT_CONNECTIONS)
+    }
+}
+
+pub(crate) fn get_warm_up_config_from_json_object(
+    object: &mut Map<String, Value>,
+) -> Result<WarmUpConfig, Error> {
+    let mut warm_up_config = WarmUpConfig::default();
+
+    if let Some(connections) = get_optional_value_from_json_object(object, CONNECTIONS_KEY)? {
+        warm_up_config.connections = connections;
+    }
+
+    if let Some(channels_per_connection) =
+        get_optional_value_from_json_object(object, CHANNELS_PER_CONNECTION_KEY)?
+    {
+        warm_up_config.channels_per_connection = channels_per_connection;
+    }
+
+    Ok(warm_up_config)
+}
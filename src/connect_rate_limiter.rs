@@ -0,0 +1,46 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::config::connect_rate_limit_config::ConnectRateLimitConfig;
+
+/// Sliding window of the latest connection attempts.
+pub(crate) struct ConnectRateLimiter {
+    config: ConnectRateLimitConfig,
+    attempts: VecDeque<Instant>,
+}
+
+impl ConnectRateLimiter {
+    pub(crate) fn new(config: ConnectRateLimitConfig) -> ConnectRateLimiter {
+        ConnectRateLimiter {
+            config,
+            attempts: VecDeque::new(),
+        }
+    }
+
+    /// Records an attempt at `now` if the limit allows it, or tells how long to wait
+    /// before asking again.
+    pub(crate) fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        let interval = self.config.interval();
+
+        while let Some(attempt) = self.attempts.front() {
+            if now.duration_since(*attempt) < interval {
+                break;
+            }
+
+            self.attempts.pop_front();
+        }
+
+        match self.attempts.front() {
+            Some(oldest_attempt) if self.attempts.len() >= self.config.max_connects() as usize => {
+                Err(interval - now.duration_since(*oldest_attempt))
+            }
+            _ => {
+                self.attempts.push_back(now);
+
+                Ok(())
+            }
+        }
+    }
+}
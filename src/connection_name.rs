@@ -0,0 +1,38 @@
+const SERVICE_PLACEHOLDER: &str = "{service}";
+const HOSTNAME_PLACEHOLDER: &str = "{hostname}";
+const POOL_PLACEHOLDER: &str = "{pool}";
+const INDEX_PLACEHOLDER: &str = "{index}";
+const CONNECTION_ID_PLACEHOLDER: &str = "{connection_id}";
+
+/// Values substituted into the connection name template.
+pub(crate) struct ConnectionNameContext<'a> {
+    pub service: &'a str,
+    pub hostname: &'a str,
+    pub pool: &'a str,
+    pub index: usize,
+    pub connection_id: &'a str,
+}
+
+/// Fills a connection name template in, leaving unknown placeholders untouched.
+pub(crate) fn render_connection_name(template: &str, context: &ConnectionNameContext) -> String {
+    template
+        .replace(SERVICE_PLACEHOLDER, context.service)
+        .replace(HOSTNAME_PLACEHOLDER, context.hostname)
+        .replace(POOL_PLACEHOLDER, context.pool)
+        .replace(INDEX_PLACEHOLDER, &context.index.to_string())
+        .replace(CONNECTION_ID_PLACEHOLDER, context.connection_id)
+}
+
+/// Name of the host running the service, as far as the environment tells.
+pub(crate) fn hostname() -> String {
+    if let Ok(hostname) = std::env::var("HOSTNAME") {
+        if !hostname.is_empty() {
+            return hostname;
+        }
+    }
+
+    match std::fs::read_to_string("/etc/hostname") {
+        Ok(hostname) if !hostname.trim().is_empty() => hostname.trim().to_string(),
+        _ => "unknown".to_string(),
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use rand::Rng;
+
+use crate::error::{Error, ErrorKind};
+
+const ROUND_ROBIN: &str = "round_robin";
+const LEAST_CHANNELS: &str = "least_channels";
+const RANDOM: &str = "random";
+
+/// Connection of the pool able to receive a new channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectionCandidate<'a> {
+    connection_id: &'a str,
+    channel_count: usize,
+}
+
+impl<'a> ConnectionCandidate<'a> {
+    pub fn new(connection_id: &'a str, channel_count: usize) -> ConnectionCandidate<'a> {
+        ConnectionCandidate {
+            connection_id,
+            channel_count,
+        }
+    }
+
+    pub fn connection_id(&self) -> &str {
+        self.connection_id
+    }
+
+    /// Channels leased from the connection, or being opened on it.
+    pub fn channel_count(&self) -> usize {
+        self.channel_count
+    }
+}
+
+/// Strategy picking the connection a channel is handed out from.
+pub trait ConnectionSelector: Send + Sync {
+    /// Returns the index, within `candidates`, of the selected connection.
+    ///
+    /// `candidates` is never empty.
+    fn select(&self, candidates: &[ConnectionCandidate]) -> usize;
+}
+
+/// Cycles through the connections one after the other.
+#[derive(Debug, Default)]
+pub struct RoundRobinSelector {
+    next: AtomicUsize,
+}
+
+impl RoundRobinSelector {
+    pub fn new() -> RoundRobinSelector {
+        RoundRobinSelector::default()
+    }
+}
+
+impl ConnectionSelector for RoundRobinSelector {
+    fn select(&self, candidates: &[ConnectionCandidate]) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % candidates.len()
+    }
+}
+
+/// Picks the connection with the fewest channels, the oldest one on ties.
+#[derive(Debug, Default)]
+pub struct LeastChannelsSelector;
+
+impl ConnectionSelector for LeastChannelsSelector {
+    fn select(&self, candidates: &[ConnectionCandidate]) -> usize {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, candidate)| candidate.channel_count())
+            .map(|(index, _)| index)
+            .unwrap_or_default()
+    }
+}
+
+/// Picks any connection, uniformly at random.
+#[derive(Debug, Default)]
+pub struct RandomSelector;
+
+impl ConnectionSelector for RandomSelector {
+    fn select(&self, candidates: &[ConnectionCandidate]) -> usize {
+        rand::thread_rng().gen_range(0..candidates.len())
+    }
+}
+
+/// Connection selection strategy of a pool.
+#[derive(Clone, Default)]
+pub enum ConnectionSelection {
+    RoundRobin,
+    #[default]
+    LeastChannels,
+    Random,
+    Custom(Arc<dyn ConnectionSelector>),
+}
+
+impl ConnectionSelection {
+    pub fn try_from_name(name: &str) -> Result<ConnectionSelection, Error> {
+        match name {
+            ROUND_ROBIN => Ok(ConnectionSelection::RoundRobin),
+            LEAST_CHANNELS => Ok(ConnectionSelection::LeastChannels),
+            RANDOM => Ok(ConnectionSelection::Random),
+            _ => Err(Error::new(
+                ErrorKind::ConfigFailure,
+                format!(
+                    "unknown connection selector '{}', expected '{}', '{}' or '{}'",
+                    name, ROUND_ROBIN, LEAST_CHANNELS, RANDOM
+                ),
+            )),
+        }
+    }
+
+    /// Creates the selector used by a pool; built-in ones do not share any state.
+    pub(crate) fn build(&self) -> Arc<dyn ConnectionSelector> {
+        match self {
+            ConnectionSelection::RoundRobin => Arc::new(RoundRobinSelector::new()),
+            ConnectionSelection::LeastChannels => Arc::new(LeastChannelsSelector),
+            ConnectionSelection::Random => Arc::new(RandomSelector),
+            ConnectionSelection::Custom(selector) => selector.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for ConnectionSelection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionSelection::RoundRobin => f.write_str(ROUND_ROBIN),
+            ConnectionSelection::LeastChannels => f.write_str(LEAST_CHANNELS),
+            ConnectionSelection::Random => f.write_str(RANDOM),
+            ConnectionSelection::Custom(_) => f.write_str("custom"),
+        }
+    }
+}
+
+impl PartialEq for ConnectionSelection {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ConnectionSelection::RoundRobin, ConnectionSelection::RoundRobin) => true,
+            (ConnectionSelection::LeastChannels, ConnectionSelection::LeastChannels) => true,
+            (ConnectionSelection::Random, ConnectionSelection::Random) => true,
+            (ConnectionSelection::Custom(selector), ConnectionSelection::Custom(other)) => {
+                Arc::ptr_eq(selector, other)
+            }
+            _ => false,
+        }
+    }
+}
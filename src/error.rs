@@ -5,6 +5,12 @@ pub enum ErrorKind {
     InternalFailure,
     ConfigFailure,
     StateUpdateFailure,
+    PoolExhausted,
+    AcquireTimeout,
+    Closed,
+    ConnectionBlocked,
+    RetriesExhausted,
+    CircuitOpen,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -0,0 +1,41 @@
+use serde::Serialize;
+
+use lapin::types::ChannelId;
+
+/// Notable change in the connections and channels of an `AmqpWrapper`, broadcast to the receivers
+/// returned by `AmqpWrapper::subscribe`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum WrapperEvent {
+    /// The broker stopped accepting publishes on the connection, after a memory or
+    /// disk alarm.
+    ConnectionBlocked {
+        pool: String,
+        connection_id: String,
+        node: String,
+    },
+    ConnectionUnblocked {
+        pool: String,
+        connection_id: String,
+        node: String,
+    },
+    /// The connection failed and left the pool, e.g. once the broker stopped sending
+    /// heartbeats after a network incident.
+    ConnectionDead {
+        pool: String,
+        connection_id: String,
+        node: String,
+        reason: String,
+        /// Whole heartbeat intervals elapsed since the last frame was received, 0
+        /// when no heartbeat was negotiated.
+        missed_heartbeats: u32,
+    },
+    /// The broker closed the channel after a protocol error, such as a 404 on a
+    /// passive declare or a 406 precondition failure, and it left the pool.
+    ChannelClosed {
+        pool: String,
+        connection_id: String,
+        channel_id: ChannelId,
+        reply_code: u16,
+        reply_text: String,
+    },
+}
@@ -1,4 +1,17 @@
 pub mod amqp_wrapper;
+mod backoff;
+pub mod channel_lease;
+pub mod channel_purpose;
+pub mod circuit_breaker;
+pub mod close_report;
 pub mod config;
+mod connect_rate_limiter;
+mod connection_name;
+pub mod connection_selector;
 pub mod error;
+pub mod events;
+mod read_clock;
+pub mod stats;
 mod tests;
+pub mod transaction;
+pub mod wrapper_registry;
@@ -0,0 +1,143 @@
+use std::{
+    io::{self, IoSlice, IoSliceMut},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use reactor_trait::{AsyncIOHandle, IOHandle, Reactor};
+
+/// When anything was last read from the socket of a connection, heartbeats included.
+pub(crate) struct ReadClock {
+    started_at: Instant,
+    // Time elapsed between `started_at` and the last read, in milliseconds.
+    last_read_ms: AtomicU64,
+}
+
+impl ReadClock {
+    pub(crate) fn new() -> ReadClock {
+        ReadClock {
+            started_at: Instant::now(),
+            last_read_ms: AtomicU64::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        let elapsed_ms = self.started_at.elapsed().as_millis() as u64;
+
+        self.last_read_ms.fetch_max(elapsed_ms, Ordering::Relaxed);
+    }
+
+    /// Time elapsed since anything was last read from the socket.
+    pub(crate) fn since_last_read(&self) -> Duration {
+        let last_read = Duration::from_millis(self.last_read_ms.load(Ordering::Relaxed));
+
+        self.started_at.elapsed().saturating_sub(last_read)
+    }
+}
+
+/// Reactor registering the socket of a connection with `inner`, noting every read
+/// from it on `read_clock`, since lapin keeps its own note of them to itself.
+pub(crate) struct ReadTrackingReactor {
+    inner: Arc<dyn Reactor + Send + Sync>,
+    read_clock: Arc<ReadClock>,
+}
+
+impl ReadTrackingReactor {
+    pub(crate) fn new(
+        inner: Arc<dyn Reactor + Send + Sync>,
+        read_clock: Arc<ReadClock>,
+    ) -> ReadTrackingReactor {
+        ReadTrackingReactor { inner, read_clock }
+    }
+}
+
+#[async_trait]
+impl Reactor for ReadTrackingReactor {
+    fn register(&self, socket: IOHandle) -> io::Result<Box<dyn AsyncIOHandle + Send>> {
+        Ok(Box::new(ReadTrackingHandle {
+            inner: Box::into_pin(self.inner.register(socket)?),
+            read_clock: self.read_clock.clone(),
+        }))
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.inner.sleep(duration).await;
+    }
+
+    fn interval(&self, duration: Duration) -> Box<dyn Stream<Item = Instant>> {
+        self.inner.interval(duration)
+    }
+}
+
+struct ReadTrackingHandle {
+    inner: Pin<Box<dyn AsyncIOHandle + Send>>,
+    read_clock: Arc<ReadClock>,
+}
+
+impl ReadTrackingHandle {
+    fn track(&self, poll: Poll<io::Result<usize>>) -> Poll<io::Result<usize>> {
+        if let Poll::Ready(Ok(read)) = poll {
+            if read > 0 {
+                self.read_clock.touch();
+            }
+        }
+
+        poll
+    }
+}
+
+impl AsyncRead for ReadTrackingHandle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = self.inner.as_mut().poll_read(cx, buf);
+
+        self.track(poll)
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let poll = self.inner.as_mut().poll_read_vectored(cx, bufs);
+
+        self.track(poll)
+    }
+}
+
+impl AsyncWrite for ReadTrackingHandle {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.inner.as_mut().poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.inner.as_mut().poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.as_mut().poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.as_mut().poll_close(cx)
+    }
+}
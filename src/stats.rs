@@ -0,0 +1,65 @@
+use serde::Serialize;
+
+use crate::circuit_breaker::CircuitState;
+
+/// Snapshot of the connections and channels of an `AmqpWrapper`, taken by
+/// `AmqpWrapper::stats`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct WrapperStats {
+    pub pools: Vec<PoolStats>,
+    /// State of the circuit breaker, always closed when it is not configured.
+    pub circuit_breaker: CircuitState,
+}
+
+impl WrapperStats {
+    pub fn connection_count(&self) -> usize {
+        self.pools.iter().map(|pool| pool.connections.len()).sum()
+    }
+}
+
+/// Snapshot of one pool of the wrapper, along with its counters since it was created.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PoolStats {
+    pub pool: String,
+    pub connections: Vec<ConnectionStats>,
+    /// Callers queued until a channel is released.
+    pub waiters: usize,
+    pub channels_created: u64,
+    pub connect_failures: u64,
+    /// Times a channel could not be handed out right away because of a limit,
+    /// either of the pool or of the broker.
+    pub limit_hits: u64,
+    /// Connections lost to an error, whether reported by the broker or detected
+    /// locally.
+    pub dead_connections: u64,
+    /// Dead connections from which the broker stopped sending heartbeats.
+    pub heartbeat_timeouts: u64,
+    /// Pooled channels found closed or errored, and dropped instead of being leased.
+    pub discarded_channels: u64,
+    /// Connections closed after reaching their maximum lifetime.
+    pub rotated_connections: u64,
+}
+
+/// Snapshot of one connection of a pool.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ConnectionStats {
+    pub connection_id: String,
+    pub node: String,
+    pub age_ms: u64,
+    pub state: String,
+    /// Whether the broker blocked publishing on the connection.
+    pub blocked: bool,
+    /// Channels the broker allows on the connection, if it bounds them.
+    pub channel_max: Option<u16>,
+    /// Heartbeat interval negotiated with the broker, if heartbeats are enabled.
+    pub heartbeat_secs: Option<u16>,
+    /// Whole heartbeat intervals elapsed since the last frame was received.
+    pub missed_heartbeats: u32,
+    pub leased_channels: usize,
+    pub idle_channels: usize,
+    pub pending_channels: usize,
+    /// Leased and idle channels still open.
+    pub live_channels: usize,
+    /// Leased and idle channels closed by the broker or errored.
+    pub dead_channels: usize,
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::backoff::Backoff;
+use crate::config::backoff_config::BackoffConfig;
+
+#[test]
+fn delays_grow_exponentially_up_to_the_max_delay() {
+    let config = BackoffConfig::new(Duration::from_millis(100), Duration::from_millis(1000))
+        .with_multiplier(2.0)
+        .with_jitter(0.0);
+
+    let mut backoff = Backoff::new(config);
+
+    let delays: Vec<Duration> = (0..6).map(|_| backoff.next_delay()).collect();
+
+    assert_eq!(
+        delays,
+        vec![
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            Duration::from_millis(400),
+            Duration::from_millis(800),
+            Duration::from_millis(1000),
+            Duration::from_millis(1000),
+        ]
+    );
+}
+
+#[test]
+fn jitter_only_shortens_delays() {
+    let config = BackoffConfig::new(Duration::from_millis(1000), Duration::from_millis(1000))
+        .with_jitter(0.5);
+
+    let mut backoff = Backoff::new(config);
+
+    for _ in 0..100 {
+        let delay = backoff.next_delay();
+
+        assert!(delay >= Duration::from_millis(500));
+        assert!(delay <= Duration::from_millis(1000));
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+use crate::config::circuit_breaker_config::CircuitBreakerConfig;
+
+const RESET_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn opens_after_consecutive_failures() {
+    let mut circuit_breaker = CircuitBreaker::new(CircuitBreakerConfig::new(2, RESET_TIMEOUT));
+    let now = Instant::now();
+
+    circuit_breaker.on_failure(now);
+    assert_eq!(circuit_breaker.state(), CircuitState::Closed);
+
+    circuit_breaker.on_success();
+    circuit_breaker.on_failure(now);
+    assert!(circuit_breaker.try_acquire(now));
+
+    circuit_breaker.on_failure(now);
+    assert_eq!(circuit_breaker.state(), CircuitState::Open);
+    assert!(!circuit_breaker.try_acquire(now + Duration::from_secs(1)));
+}
+
+#[test]
+fn lets_a_single_probe_through_after_the_reset_timeout() {
+    let mut circuit_breaker = CircuitBreaker::new(CircuitBreakerConfig::new(1, RESET_TIMEOUT));
+    let now = Instant::now();
+
+    circuit_breaker.on_failure(now);
+
+    let probe_time = now + RESET_TIMEOUT;
+
+    assert!(circuit_breaker.try_acquire(probe_time));
+    assert_eq!(circuit_breaker.state(), CircuitState::HalfOpen);
+    assert!(!circuit_breaker.try_acquire(probe_time));
+
+    circuit_breaker.on_success();
+
+    assert_eq!(circuit_breaker.state(), CircuitState::Closed);
+    assert!(circuit_breaker.try_acquire(probe_time));
+}
+
+#[test]
+fn failed_probe_opens_the_circuit_again() {
+    let mut circuit_breaker = CircuitBreaker::new(CircuitBreakerConfig::new(3, RESET_TIMEOUT));
+    let now = Instant::now();
+
+    for _ in 0..3 {
+        circuit_breaker.on_failure(now);
+    }
+
+    let probe_time = now + RESET_TIMEOUT;
+
+    assert!(circuit_breaker.try_acquire(probe_time));
+
+    circuit_breaker.on_failure(probe_time);
+
+    assert_eq!(circuit_breaker.state(), CircuitState::Open);
+    assert!(!circuit_breaker.try_acquire(probe_time + Duration::from_secs(1)));
+}
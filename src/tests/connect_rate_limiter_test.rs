@@ -0,0 +1,25 @@
+use std::time::{Duration, Instant};
+
+use crate::config::connect_rate_limit_config::ConnectRateLimitConfig;
+use crate::connect_rate_limiter::ConnectRateLimiter;
+
+#[test]
+fn attempts_beyond_the_limit_wait_for_the_window_to_slide() {
+    let mut connect_rate_limiter =
+        ConnectRateLimiter::new(ConnectRateLimitConfig::new(2, Duration::from_secs(1)));
+    let now = Instant::now();
+
+    assert_eq!(connect_rate_limiter.try_acquire(now), Ok(()));
+    assert_eq!(
+        connect_rate_limiter.try_acquire(now + Duration::from_millis(400)),
+        Ok(())
+    );
+    assert_eq!(
+        connect_rate_limiter.try_acquire(now + Duration::from_millis(600)),
+        Err(Duration::from_millis(400))
+    );
+    assert_eq!(
+        connect_rate_limiter.try_acquire(now + Duration::from_secs(1)),
+        Ok(())
+    );
+}
@@ -0,0 +1,35 @@
+use crate::connection_name::{render_connection_name, ConnectionNameContext};
+
+#[test]
+fn every_placeholder_is_filled_in() {
+    let context = ConnectionNameContext {
+        service: "billing",
+        hostname: "billing-7d9f",
+        pool: "publish",
+        index: 2,
+        connection_id: "0c9e",
+    };
+
+    let connection_name = render_connection_name(
+        "{service}@{hostname}/{pool}#{index} ({connection_id})",
+        &context,
+    );
+
+    assert_eq!(connection_name, "billing@billing-7d9f/publish#2 (0c9e)");
+}
+
+#[test]
+fn unknown_placeholders_are_left_untouched() {
+    let context = ConnectionNameContext {
+        service: "billing",
+        hostname: "billing-7d9f",
+        pool: "shared",
+        index: 0,
+        connection_id: "0c9e",
+    };
+
+    assert_eq!(
+        render_connection_name("{service}-{region}", &context),
+        "billing-{region}"
+    );
+}
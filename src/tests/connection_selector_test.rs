@@ -0,0 +1,38 @@
+use crate::connection_selector::{
+    ConnectionCandidate, ConnectionSelector, LeastChannelsSelector, RandomSelector,
+    RoundRobinSelector,
+};
+
+fn candidates() -> Vec<ConnectionCandidate<'static>> {
+    vec![
+        ConnectionCandidate::new("first", 4),
+        ConnectionCandidate::new("second", 1),
+        ConnectionCandidate::new("third", 1),
+    ]
+}
+
+#[test]
+fn round_robin_cycles_through_every_connection() {
+    let selector = RoundRobinSelector::new();
+    let candidates = candidates();
+
+    let selected: Vec<usize> = (0..6).map(|_| selector.select(&candidates)).collect();
+
+    assert_eq!(selected, vec![0, 1, 2, 0, 1, 2]);
+}
+
+#[test]
+fn least_channels_picks_the_oldest_least_loaded_connection() {
+    let candidates = candidates();
+
+    assert_eq!(LeastChannelsSelector.select(&candidates), 1);
+}
+
+#[test]
+fn random_stays_within_the_candidates() {
+    let candidates = candidates();
+
+    for _ in 0..100 {
+        assert!(RandomSelector.select(&candidates) < candidates.len());
+    }
+}
@@ -0,0 +1,19 @@
+use crate::config::endpoint_order::EndpointOrder;
+
+#[test]
+fn in_order_always_starts_from_the_first_endpoint() {
+    assert_eq!(EndpointOrder::InOrder.endpoint_indexes(3, 2), vec![0, 1, 2]);
+}
+
+#[test]
+fn sticky_starts_from_the_last_connected_endpoint() {
+    assert_eq!(EndpointOrder::Sticky.endpoint_indexes(3, 2), vec![2, 0, 1]);
+}
+
+#[test]
+fn shuffled_tries_every_endpoint_once() {
+    let mut indexes = EndpointOrder::Shuffled.endpoint_indexes(5, 0);
+    indexes.sort();
+
+    assert_eq!(indexes, vec![0, 1, 2, 3, 4]);
+}
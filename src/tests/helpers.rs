@@ -0,0 +1,13 @@
+use lapin::{tcp::OwnedTLSConfig, ConnectionProperties};
+
+use crate::config::amqp_connect_config::AmqpConnectConfig;
+
+/// Config connecting to `uri` without TLS, the other settings left to their defaults.
+pub(crate) fn base_config(uri: &str) -> AmqpConnectConfig {
+    let tls = OwnedTLSConfig {
+        cert_chain: None,
+        identity: None,
+    };
+
+    AmqpConnectConfig::new(uri.to_string(), ConnectionProperties::default(), tls)
+}
@@ -1,2 +1,20 @@
 #[cfg(test)]
 mod amqp_wrapper_test;
+#[cfg(test)]
+mod backoff_test;
+#[cfg(test)]
+mod circuit_breaker_test;
+#[cfg(test)]
+mod connect_rate_limiter_test;
+#[cfg(test)]
+mod connection_name_test;
+#[cfg(test)]
+mod connection_selector_test;
+#[cfg(test)]
+mod endpoint_order_test;
+#[cfg(test)]
+mod fake_broker;
+#[cfg(test)]
+mod helpers;
+#[cfg(test)]
+mod wrapper_registry_test;
@@ -0,0 +1,147 @@
+use lapin::{
+    options::{BasicAckOptions, BasicPublishOptions},
+    BasicProperties, Channel,
+};
+use tokio::runtime::Handle;
+
+use crate::channel_lease::ChannelLease;
+use crate::error::{Error, ErrorKind};
+
+// Profile under which the channels in tx mode are pooled, apart from the configured ones.
+pub(crate) const TRANSACTION_PROFILE: &str = "@transaction";
+
+/// AMQP transaction begun by `AmqpWrapper`, holding a channel in tx mode.
+///
+/// Publishes and acks made through it take effect once it is committed. Dropping it
+/// uncommitted rolls it back in the background; call `rollback` to wait for that.
+#[derive(Debug)]
+pub struct Transaction {
+    channel: Channel,
+    connection_id: String,
+    // Taken once the transaction ends.
+    channel_lease: Option<ChannelLease>,
+}
+
+impl Transaction {
+    pub(crate) fn new(channel_lease: ChannelLease) -> Transaction {
+        Transaction {
+            channel: channel_lease.channel().clone(),
+            connection_id: channel_lease.connection_id().to_string(),
+            channel_lease: Some(channel_lease),
+        }
+    }
+
+    /// Channel of the transaction, for the operations not exposed here.
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    pub fn connection_id(&self) -> &str {
+        self.connection_id.as_str()
+    }
+
+    pub async fn basic_publish(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        options: BasicPublishOptions,
+        payload: &[u8],
+        properties: BasicProperties,
+    ) -> Result<(), Error> {
+        match self
+            .channel()
+            .basic_publish(exchange, routing_key, options, payload, properties)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(error) => Err(Error::new(
+                ErrorKind::InternalFailure,
+                format!("failed to publish within the transaction: {}", error),
+            )),
+        }
+    }
+
+    pub async fn basic_ack(
+        &self,
+        delivery_tag: u64,
+        options: BasicAckOptions,
+    ) -> Result<(), Error> {
+        match self.channel().basic_ack(delivery_tag, options).await {
+            Ok(()) => Ok(()),
+            Err(error) => Err(Error::new(
+                ErrorKind::InternalFailure,
+                format!("failed to ack within the transaction: {}", error),
+            )),
+        }
+    }
+
+    /// Commits the publishes and acks made within the transaction.
+    ///
+    /// The channel is closed when the commit fails, which discards them.
+    pub async fn commit(mut self) -> Result<(), Error> {
+        let channel_lease = self.take_channel_lease()?;
+
+        match channel_lease.tx_commit().await {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                let _ = channel_lease.close().await;
+
+                Err(Error::new(
+                    ErrorKind::InternalFailure,
+                    format!("failed to commit the transaction: {}", error),
+                ))
+            }
+        }
+    }
+
+    /// Discards the publishes and acks made within the transaction.
+    pub async fn rollback(mut self) -> Result<(), Error> {
+        rollback(self.take_channel_lease()?).await
+    }
+
+    fn take_channel_lease(&mut self) -> Result<ChannelLease, Error> {
+        match self.channel_lease.take() {
+            Some(channel_lease) => Ok(channel_lease),
+            None => Err(Error::new(
+                ErrorKind::InternalFailure,
+                "the transaction has already ended",
+            )),
+        }
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        let channel_lease = match self.channel_lease.take() {
+            Some(channel_lease) => channel_lease,
+            None => return,
+        };
+
+        // without a runtime, the channel is not reused and lapin closes it once
+        // dropped, which discards the transaction as well
+        match Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    let _ = rollback(channel_lease).await;
+                });
+            }
+            Err(_) => channel_lease.discard(),
+        }
+    }
+}
+
+/// Rolls back the transaction of `channel_lease`, closing its channel when that fails
+/// so it cannot be reused with work still pending.
+async fn rollback(channel_lease: ChannelLease) -> Result<(), Error> {
+    match channel_lease.tx_rollback().await {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            let _ = channel_lease.close().await;
+
+            Err(Error::new(
+                ErrorKind::InternalFailure,
+                format!("failed to roll back the transaction: {}", error),
+            ))
+        }
+    }
+}
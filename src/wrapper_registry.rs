@@ -0,0 +1,350 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+use lapin::uri::AMQPUri;
+use tokio::runtime::Handle;
+
+use crate::amqp_wrapper::AmqpWrapper;
+use crate::channel_purpose::ChannelPurpose;
+use crate::close_report::CloseReport;
+use crate::config::amqp_connect_config::AmqpConnectConfig;
+use crate::config::registry_config::RegistryConfig;
+use crate::error::{Error, ErrorKind};
+use crate::stats::WrapperStats;
+
+// Time given to an idle wrapper to close once evicted.
+const EVICTION_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+// Shortest period between two background evictions, whatever the idle timeout.
+const MIN_EVICTION_INTERVAL: Duration = Duration::from_millis(1);
+
+struct Tenant {
+    wrapper: AmqpWrapper,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+struct RegistryState {
+    tenants: HashMap<String, Tenant>,
+    closing: bool,
+}
+
+struct WrapperRegistryInner {
+    base_config: AmqpConnectConfig,
+    registry_config: RegistryConfig,
+    state: Mutex<RegistryState>,
+    evictor_started: AtomicBool,
+}
+
+impl WrapperRegistryInner {
+    fn lock_state(&self) -> Result<MutexGuard<'_, RegistryState>, Error> {
+        match self.state.lock() {
+            Ok(state) => Ok(state),
+            Err(_) => Err(Error::new(
+                ErrorKind::StateUpdateFailure,
+                "failed to lock the state of the tenants",
+            )),
+        }
+    }
+
+    /// Base config pointed at the vhost of `tenant`, with its own pool limits if any
+    /// applied to every pool of its wrapper.
+    fn tenant_config(&self, tenant: &str) -> Result<AmqpConnectConfig, Error> {
+        let vhost = self.registry_config.tenant_vhost(tenant);
+        let mut uris = Vec::new();
+
+        for uri in self.base_config.uris() {
+            uris.push(uri_with_vhost(uri, vhost)?);
+        }
+
+        let mut tenant_config = self.base_config.clone().with_uris(uris);
+
+        let pool_config = match self.registry_config.tenant_pool_config(tenant) {
+            Some(pool_config) => pool_config,
+            None => return Ok(tenant_config),
+        };
+
+        tenant_config = tenant_config.with_pool_config(pool_config.clone());
+
+        if self
+            .base_config
+            .dedicated_pool_config(ChannelPurpose::Publish)
+            .is_some()
+        {
+            tenant_config = tenant_config.with_publish_pool_config(pool_config.clone());
+        }
+
+        if self
+            .base_config
+            .dedicated_pool_config(ChannelPurpose::Consume)
+            .is_some()
+        {
+            tenant_config = tenant_config.with_consume_pool_config(pool_config.clone());
+        }
+
+        for group in self.base_config.group_pool_configs().keys() {
+            tenant_config = tenant_config.with_affinity_group(group.clone(), pool_config.clone());
+        }
+
+        Ok(tenant_config)
+    }
+
+    fn start_evictor(self: &Arc<Self>) {
+        let idle_timeout = match self.registry_config.idle_timeout() {
+            Some(idle_timeout) => idle_timeout,
+            None => return,
+        };
+
+        // Without a runtime, idle tenants are only evicted on demand.
+        let runtime = match Handle::try_current() {
+            Ok(runtime) => runtime,
+            Err(_) => return,
+        };
+
+        if self.evictor_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        runtime.spawn(evictor(
+            Arc::downgrade(self),
+            (idle_timeout / 2).max(MIN_EVICTION_INTERVAL),
+        ));
+    }
+
+    async fn evict_idle_tenants(&self) -> Result<Vec<String>, Error> {
+        let idle_timeout = match self.registry_config.idle_timeout() {
+            Some(idle_timeout) => idle_timeout,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut evicted_tenants = Vec::new();
+
+        {
+            let mut state = self.lock_state()?;
+            let mut idle_tenants = Vec::new();
+
+            for (name, tenant) in state.tenants.iter() {
+                if tenant.last_used.elapsed() >= idle_timeout
+                    && !is_in_use(&tenant.wrapper.stats()?)
+                {
+                    idle_tenants.push(name.clone());
+                }
+            }
+
+            for name in idle_tenants {
+                if let Some(tenant) = state.tenants.remove(&name) {
+                    evicted_tenants.push((name, tenant.wrapper));
+                }
+            }
+        }
+
+        let mut evicted_names = Vec::new();
+
+        for (name, wrapper) in evicted_tenants {
+            // the tenant is forgotten either way, the others still have to be closed
+            if let Err(error) = wrapper.close(EVICTION_CLOSE_TIMEOUT).await {
+                tracing::warn!(
+                    tenant = %name,
+                    %error,
+                    "failed to close the wrapper of an evicted tenant"
+                );
+            }
+
+            evicted_names.push(name);
+        }
+
+        evicted_names.sort();
+
+        Ok(evicted_names)
+    }
+}
+
+/// Lazily created `AmqpWrapper`s, one per tenant, each connected to the vhost of
+/// its tenant with the base config shared by all of them.
+///
+/// Cloning it is cheap: every clone shares the same wrappers.
+#[derive(Clone)]
+pub struct WrapperRegistry {
+    inner: Arc<WrapperRegistryInner>,
+}
+
+impl WrapperRegistry {
+    pub fn try_new(
+        base_config: AmqpConnectConfig,
+        registry_config: RegistryConfig,
+    ) -> Result<WrapperRegistry, Error> {
+        base_config.validate()?;
+        registry_config.validate()?;
+
+        Ok(WrapperRegistry {
+            inner: Arc::new(WrapperRegistryInner {
+                base_config,
+                registry_config,
+                state: Mutex::new(RegistryState::default()),
+                evictor_started: AtomicBool::new(false),
+            }),
+        })
+    }
+
+    /// Wrapper of `tenant`, created on its first request.
+    ///
+    /// The wrapper of an evicted tenant is closed, so it should be requested again
+    /// here rather than kept around.
+    pub fn try_get(&self, tenant: &str) -> Result<AmqpWrapper, Error> {
+        let wrapper = {
+            let mut state = self.inner.lock_state()?;
+
+            if state.closing {
+                return Err(Error::new(ErrorKind::Closed, "the registry is closed"));
+            }
+
+            if let Some(tenant) = state.tenants.get_mut(tenant) {
+                tenant.last_used = Instant::now();
+
+                return Ok(tenant.wrapper.clone());
+            }
+
+            let wrapper = AmqpWrapper::try_new(self.inner.tenant_config(tenant)?)?;
+
+            state.tenants.insert(
+                tenant.to_string(),
+                Tenant {
+                    wrapper: wrapper.clone(),
+                    last_used: Instant::now(),
+                },
+            );
+
+            wrapper
+        };
+
+        self.inner.start_evictor();
+
+        Ok(wrapper)
+    }
+
+    /// Tenants with a wrapper, sorted by name.
+    pub fn tenants(&self) -> Result<Vec<String>, Error> {
+        let mut tenants: Vec<String> = self.inner.lock_state()?.tenants.keys().cloned().collect();
+        tenants.sort();
+
+        Ok(tenants)
+    }
+
+    /// Closes and forgets the wrappers of the tenants idle for longer than the
+    /// configured timeout, returning their names.
+    ///
+    /// This also runs periodically in the background once a wrapper was created
+    /// from within a Tokio runtime.
+    pub async fn evict_idle_tenants(&self) -> Result<Vec<String>, Error> {
+        self.inner.evict_idle_tenants().await
+    }
+
+    /// Stops creating wrappers and closes every existing one, reporting what could
+    /// not be closed within `timeout` for each tenant.
+    ///
+    /// The tenants whose wrapper failed to close are logged and left out of the reports.
+    pub async fn close(&self, timeout: Duration) -> Result<HashMap<String, CloseReport>, Error> {
+        let tenants = {
+            let mut state = self.inner.lock_state()?;
+            state.closing = true;
+
+            std::mem::take(&mut state.tenants)
+        };
+
+        let mut reports = HashMap::new();
+
+        for (name, tenant) in tenants {
+            // the tenants are forgotten already, the others still have to be closed
+            match tenant.wrapper.close(timeout).await {
+                Ok(report) => {
+                    reports.insert(name, report);
+                }
+                Err(error) => {
+                    tracing::warn!(
+                        tenant = %name,
+                        %error,
+                        "failed to close the wrapper of a tenant"
+                    );
+                }
+            }
+        }
+
+        Ok(reports)
+    }
+}
+
+async fn evictor(registry: Weak<WrapperRegistryInner>, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+
+    loop {
+        interval.tick().await;
+
+        let registry = match registry.upgrade() {
+            Some(registry) => registry,
+            None => return,
+        };
+
+        match registry.lock_state() {
+            Ok(state) if !state.closing => (),
+            _ => return,
+        }
+
+        let _ = registry.evict_idle_tenants().await;
+    }
+}
+
+fn is_in_use(stats: &WrapperStats) -> bool {
+    stats.pools.iter().any(|pool| {
+        pool.waiters > 0
+            || pool
+                .connections
+                .iter()
+                .any(|connection| connection.leased_channels > 0 || connection.pending_channels > 0)
+    })
+}
+
+/// Points `uri` at `vhost`, keeping its credentials, authority and query.
+pub(crate) fn uri_with_vhost(uri: &str, vhost: &str) -> Result<String, Error> {
+    if let Err(error) = uri.parse::<AMQPUri>() {
+        return Err(Error::new(
+            ErrorKind::ConfigFailure,
+            format!("invalid broker uri: {}", error),
+        ));
+    }
+
+    let authority_start = uri.find("://").map(|index| index + 3).unwrap_or(0);
+    let authority_end = uri[authority_start..]
+        .find(['/', '?'])
+        .map(|index| authority_start + index)
+        .unwrap_or(uri.len());
+    let query = match uri[authority_end..].find('?') {
+        Some(index) => &uri[authority_end + index..],
+        None => "",
+    };
+
+    Ok(format!(
+        "{}/{}{}",
+        &uri[..authority_end],
+        percent_encode(vhost),
+        query
+    ))
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::new();
+
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}